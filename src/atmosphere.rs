@@ -0,0 +1,204 @@
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+
+// Muestras a lo largo del rayo de vista y del rayo hacia el sol en cada una
+const VIEW_SAMPLES: usize = 16;
+const LIGHT_SAMPLES: usize = 8;
+
+// Atmósfera de dispersión simple (Rayleigh + Mie) alrededor de un planeta esférico.
+// Las distancias están en unidades del mundo y los coeficientes por unidad del mundo
+#[derive(Debug, Clone, Copy)]
+pub struct Atmosphere {
+    pub center: Vec3,
+    pub planet_radius: f32,
+    pub atmosphere_radius: f32,
+    // Coeficientes de dispersión de Rayleigh para rojo, verde y azul: el azul se dispersa más
+    pub rayleigh: Vec3,
+    // Altura a la que la densidad de cada componente cae a 1/e
+    pub rayleigh_scale_height: f32,
+    // La dispersión de Mie (polvo, bruma) es gris; la extinción es algo mayor por absorción
+    pub mie: f32,
+    pub mie_scale_height: f32,
+    // Asimetría de Henyey-Greenstein: cerca de 1 la bruma dispersa casi todo hacia delante
+    pub mie_anisotropy: f32,
+    // Dirección normalizada desde el planeta hacia el sol y la luz que llega de él
+    pub sun_direction: Vec3,
+    pub sun_radiance: Color,
+}
+
+impl Atmosphere {
+    // Atmósfera parecida a la terrestre escalada al radio del planeta. Se exagera el
+    // grosor para que se vea a esta resolución, conservando la profundidad óptica vertical
+    pub fn new(center: Vec3, planet_radius: f32, sun_direction: Vec3) -> Self {
+        let rayleigh_scale_height = 0.02 * planet_radius;
+        let mie_scale_height = 0.005 * planet_radius;
+
+        Atmosphere {
+            center,
+            planet_radius,
+            atmosphere_radius: planet_radius * 1.12,
+            // Profundidades ópticas verticales de la Tierra: 0.046, 0.108 y 0.265
+            rayleigh: Vec3::new(0.046, 0.108, 0.265) / rayleigh_scale_height,
+            rayleigh_scale_height,
+            mie: 0.04 / mie_scale_height,
+            mie_scale_height,
+            mie_anisotropy: 0.76,
+            sun_direction: sun_direction.normalize(),
+            sun_radiance: Color::from_f32(1.0, 1.0, 1.0) * 10.0,
+        }
+    }
+
+    // `rayleigh` en profundidad óptica vertical por canal, como en `new`
+    pub fn with_rayleigh(self, rayleigh: Vec3, scale_height: f32) -> Self {
+        Atmosphere { rayleigh: rayleigh / scale_height, rayleigh_scale_height: scale_height, ..self }
+    }
+
+    pub fn with_mie(self, mie: f32, scale_height: f32, anisotropy: f32) -> Self {
+        Atmosphere { mie: mie / scale_height, mie_scale_height: scale_height, mie_anisotropy: anisotropy, ..self }
+    }
+
+    pub fn with_sun_radiance(self, sun_radiance: Color) -> Self {
+        Atmosphere { sun_radiance, ..self }
+    }
+
+    // Luz dispersada hacia `origin` por el aire que atraviesa el rayo (ya multiplicada
+    // por su opacidad) y alfa = 1 - transmitancia media, para mezclar premultiplicado
+    pub fn scatter(&self, origin: &Vec3, direction: &Vec3) -> Color {
+        let Some((near, far)) = self.intersect(origin, direction, self.atmosphere_radius) else {
+            return Color::black().with_alpha(0.0);
+        };
+        let near = near.max(0.0);
+        // El rayo termina en la superficie si la alcanza
+        let far = match self.intersect(origin, direction, self.planet_radius) {
+            Some((hit, _)) if hit > 0.0 => hit,
+            _ => far,
+        };
+        if far <= near {
+            return Color::black().with_alpha(0.0);
+        }
+
+        let step = (far - near) / VIEW_SAMPLES as f32;
+        let mut view_depth = (0.0, 0.0);
+        let mut rayleigh_sum = Vec3::zeros();
+        let mut mie_sum = Vec3::zeros();
+
+        for i in 0..VIEW_SAMPLES {
+            let point = origin + direction * (near + (i as f32 + 0.5) * step);
+            let (rayleigh_density, mie_density) = self.density(&point);
+            view_depth.0 += rayleigh_density * step;
+            view_depth.1 += mie_density * step;
+
+            // Los puntos en la sombra del planeta no reciben luz del sol
+            let Some(light_depth) = self.light_depth(&point) else {
+                continue;
+            };
+            let attenuation = self.transmittance(view_depth.0 + light_depth.0, view_depth.1 + light_depth.1);
+            rayleigh_sum += attenuation * (rayleigh_density * step);
+            mie_sum += attenuation * (mie_density * step);
+        }
+
+        let cos_theta = direction.dot(&self.sun_direction);
+        let rayleigh_phase = 3.0 / (16.0 * PI) * (1.0 + cos_theta * cos_theta);
+        let mie_phase = henyey_greenstein(cos_theta, self.mie_anisotropy);
+
+        let scattered = rayleigh_sum.component_mul(&self.rayleigh) * rayleigh_phase + mie_sum * (self.mie * mie_phase);
+        let transmittance = self.transmittance(view_depth.0, view_depth.1);
+        let alpha = 1.0 - (transmittance.x + transmittance.y + transmittance.z) / 3.0;
+
+        Color::from_f32(scattered.x, scattered.y, scattered.z)
+            .blend_multiply(&self.sun_radiance)
+            .with_alpha(alpha)
+    }
+
+    fn density(&self, point: &Vec3) -> (f32, f32) {
+        let height = ((point - self.center).magnitude() - self.planet_radius).max(0.0);
+        ((-height / self.rayleigh_scale_height).exp(), (-height / self.mie_scale_height).exp())
+    }
+
+    // Densidad integrada desde el punto hasta el borde de la atmósfera en dirección al sol,
+    // o None si el planeta tapa el sol
+    fn light_depth(&self, point: &Vec3) -> Option<(f32, f32)> {
+        if let Some((_, exit)) = self.intersect(point, &self.sun_direction, self.planet_radius) {
+            if exit > 0.0 {
+                return None;
+            }
+        }
+        let (_, far) = self.intersect(point, &self.sun_direction, self.atmosphere_radius)?;
+
+        let step = far.max(0.0) / LIGHT_SAMPLES as f32;
+        let mut depth = (0.0, 0.0);
+        for i in 0..LIGHT_SAMPLES {
+            let sample = point + self.sun_direction * ((i as f32 + 0.5) * step);
+            let (rayleigh_density, mie_density) = self.density(&sample);
+            depth.0 += rayleigh_density * step;
+            depth.1 += mie_density * step;
+        }
+        Some(depth)
+    }
+
+    // Fracción de la luz que sobrevive a las densidades integradas dadas, por canal
+    fn transmittance(&self, rayleigh_depth: f32, mie_depth: f32) -> Vec3 {
+        let optical_depth = self.rayleigh * rayleigh_depth + Vec3::repeat(self.mie * 1.1 * mie_depth);
+        optical_depth.map(|tau| (-tau).exp())
+    }
+
+    // Distancias de entrada y salida del rayo en la esfera de `radius` centrada en el planeta
+    fn intersect(&self, origin: &Vec3, direction: &Vec3, radius: f32) -> Option<(f32, f32)> {
+        let offset = origin - self.center;
+        let b = offset.dot(direction);
+        let c = offset.dot(&offset) - radius * radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        Some((-b - root, -b + root))
+    }
+}
+
+fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let g2 = g * g;
+    (1.0 - g2) / (4.0 * PI * (1.0 + g2 - 2.0 * g * cos_theta).powf(1.5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn earth_like(sun_direction: Vec3) -> Atmosphere {
+        Atmosphere::new(Vec3::zeros(), 1.0, sun_direction)
+    }
+
+    #[test]
+    fn rays_that_miss_the_atmosphere_are_transparent() {
+        let color = earth_like(Vec3::new(0.0, 0.0, 1.0)).scatter(&Vec3::new(0.0, 3.0, 5.0), &Vec3::new(0.0, 0.0, -1.0));
+        assert!(color.is_black() && color.a() == 0.0);
+    }
+
+    #[test]
+    fn lit_limb_is_blue_and_night_limb_is_dark() {
+        // Rayo rasante a 1.03 radios del centro, con el sol sobre ese lado o tras el planeta
+        let (origin, direction) = (Vec3::new(-5.0, 1.03, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let day = earth_like(Vec3::new(0.0, 1.0, 0.0)).scatter(&origin, &direction);
+        let night = earth_like(Vec3::new(0.0, -1.0, 0.0)).scatter(&origin, &direction);
+
+        assert!(day.b() > day.g() && day.g() > day.r(), "{}", day);
+        assert!(day.a() > 0.0);
+        assert!(night.luminance() < day.luminance() * 0.1, "{} {}", night, day);
+    }
+
+    #[test]
+    fn grazing_sun_is_reddened_at_the_terminator() {
+        // Con el sol en el horizonte la luz cruza mucho aire cerca de la superficie y pierde el azul
+        let atmosphere = earth_like(Vec3::new(1.0, 0.0, 0.0));
+        let (rayleigh_depth, mie_depth) = atmosphere.light_depth(&Vec3::new(0.0, 1.01, 0.0)).unwrap();
+        let sunlight = atmosphere.transmittance(rayleigh_depth, mie_depth);
+        assert!(sunlight.x > sunlight.z, "{:?}", sunlight);
+
+        // Rayo rasante hacia ese sol: el cielo del atardecer es rojizo
+        let sunset = atmosphere.scatter(&Vec3::new(-5.0, 1.01, 0.0), &Vec3::new(1.0, 0.0, 0.0));
+        assert!(sunset.r() > sunset.b(), "{}", sunset);
+    }
+}
+
@@ -0,0 +1,56 @@
+use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
+use std::f32::consts::PI;
+
+pub struct Camera {
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    pub fn new(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        Camera {
+            eye,
+            target,
+            up,
+            fov: PI / 4.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        look_at(&self.eye, &self.target, &self.up)
+    }
+
+    pub fn projection_matrix(&self, width: f32, height: f32) -> Mat4 {
+        perspective(width / height, self.fov, self.near, self.far)
+    }
+
+    // Gira la cámara alrededor del objetivo manteniendo la distancia
+    pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        let offset = self.eye - self.target;
+        let radius = offset.magnitude();
+
+        let yaw = offset.z.atan2(offset.x) + delta_yaw;
+        let pitch = ((offset.y / radius).asin() + delta_pitch).clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+
+        self.eye = self.target + Vec3::new(
+            radius * pitch.cos() * yaw.cos(),
+            radius * pitch.sin(),
+            radius * pitch.cos() * yaw.sin(),
+        );
+    }
+
+    // Acerca o aleja la cámara sobre la línea de visión
+    pub fn zoom(&mut self, delta: f32) {
+        let direction = (self.target - self.eye).normalize();
+        let distance = (self.target - self.eye).magnitude();
+        let new_distance = (distance - delta).max(self.near);
+
+        self.eye = self.target - direction * new_distance;
+    }
+}
@@ -0,0 +1,147 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+use crate::vertex::Vertex;
+
+// Planos del frustum en espacio de recorte: un punto está dentro si dot(plano, posición) >= 0
+const FRUSTUM_PLANES: [[f32; 4]; 6] = [
+    [1.0, 0.0, 0.0, 1.0],   // izquierdo:  x + w >= 0
+    [-1.0, 0.0, 0.0, 1.0],  // derecho:    w - x >= 0
+    [0.0, 1.0, 0.0, 1.0],   // inferior:   y + w >= 0
+    [0.0, -1.0, 0.0, 1.0],  // superior:   w - y >= 0
+    [0.0, 0.0, 1.0, 1.0],   // cercano:    z + w >= 0
+    [0.0, 0.0, -1.0, 1.0],  // lejano:     w - z >= 0
+];
+
+fn plane_distance(plane: &[f32; 4], position: &Vec4) -> f32 {
+    plane[0] * position.x + plane[1] * position.y + plane[2] * position.z + plane[3] * position.w
+}
+
+pub fn clip_triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<[Vertex; 3]> {
+    let mut fully_inside = true;
+
+    for plane in &FRUSTUM_PLANES {
+        let d1 = plane_distance(plane, &v1.clip_position);
+        let d2 = plane_distance(plane, &v2.clip_position);
+        let d3 = plane_distance(plane, &v3.clip_position);
+
+        // Todo el triángulo está fuera de un mismo plano: se descarta
+        if d1 < 0.0 && d2 < 0.0 && d3 < 0.0 {
+            return Vec::new();
+        }
+        if d1 < 0.0 || d2 < 0.0 || d3 < 0.0 {
+            fully_inside = false;
+        }
+    }
+
+    if fully_inside {
+        return vec![[v1.clone(), v2.clone(), v3.clone()]];
+    }
+
+    let mut polygon = vec![v1.clone(), v2.clone(), v3.clone()];
+    for plane in &FRUSTUM_PLANES {
+        polygon = clip_polygon(&polygon, plane);
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+    }
+
+    // Triangulamos el polígono recortado en abanico
+    (1..polygon.len() - 1)
+        .map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()])
+        .collect()
+}
+
+// Sutherland-Hodgman contra un solo plano
+fn clip_polygon(polygon: &[Vertex], plane: &[f32; 4]) -> Vec<Vertex> {
+    let mut output = Vec::with_capacity(polygon.len() + 1);
+
+    for i in 0..polygon.len() {
+        let current = &polygon[i];
+        let next = &polygon[(i + 1) % polygon.len()];
+
+        let current_distance = plane_distance(plane, &current.clip_position);
+        let next_distance = plane_distance(plane, &next.clip_position);
+
+        if current_distance >= 0.0 {
+            output.push(current.clone());
+        }
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            output.push(current.lerp(next, t));
+        }
+    }
+
+    output
+}
+
+// Recorte paramétrico de un segmento contra los mismos planos
+pub fn clip_line(a: &Vertex, b: &Vertex) -> Option<[Vertex; 2]> {
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+
+    for plane in &FRUSTUM_PLANES {
+        let da = plane_distance(plane, &a.clip_position);
+        let db = plane_distance(plane, &b.clip_position);
+
+        if da < 0.0 && db < 0.0 {
+            return None;
+        }
+        if da < 0.0 {
+            t0 = t0.max(da / (da - db));
+        } else if db < 0.0 {
+            t1 = t1.min(da / (da - db));
+        }
+    }
+
+    if t0 > t1 {
+        return None;
+    }
+    Some([a.lerp(b, t0), a.lerp(b, t1)])
+}
+
+// División de perspectiva y transformación de viewport
+pub fn to_screen(vertex: &Vertex, viewport_matrix: &Mat4) -> Vertex {
+    let clip = vertex.clip_position;
+    let ndc_position = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen_position = viewport_matrix * ndc_position;
+
+    let mut screen_vertex = vertex.clone();
+    screen_vertex.transformed_position = Vec3::new(
+        screen_position.x,
+        screen_position.y,
+        screen_position.z,
+    );
+    screen_vertex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    fn clip_vertex(x: f32, y: f32, z: f32, w: f32) -> Vertex {
+        let mut vertex = Vertex::new(Vec3::new(x, y, z), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+        vertex.clip_position = Vec4::new(x, y, z, w);
+        vertex
+    }
+
+    #[test]
+    fn triangle_crossing_the_near_plane_becomes_a_quad_inside_the_frustum() {
+        // El tercer vértice queda detrás del plano cercano (z + w < 0)
+        let triangles = clip_triangle(
+            &clip_vertex(0.0, 0.0, 0.5, 1.0),
+            &clip_vertex(0.5, 0.0, 0.5, 1.0),
+            &clip_vertex(0.0, 0.5, -3.0, 1.0),
+        );
+
+        assert_eq!(triangles.len(), 2);
+        for vertex in triangles.iter().flatten() {
+            let clip = vertex.clip_position;
+            assert!(clip.w >= clip.z.abs() - 1e-5, "{:?}", clip);
+        }
+    }
+
+    #[test]
+    fn line_outside_the_frustum_is_discarded() {
+        // Los dos extremos a la derecha del plano derecho (x > w)
+        assert!(clip_line(&clip_vertex(2.0, -0.5, 0.0, 1.0), &clip_vertex(3.0, 0.5, 0.0, 1.0)).is_none());
+    }
+}
@@ -1,90 +1,134 @@
 use std::fmt;
 use std::ops::Mul;
 use std::ops::Add;
+use std::sync::OnceLock;
 
-#[derive(Debug, Clone, Copy)]
+// Canales en luz lineal y punto flotante, sin límite superior (HDR). Los colores de
+// paleta (u8 y hex) están codificados en sRGB y se decodifican al crearlos; to_hex
+// vuelve a codificar en sRGB después del tone mapping del framebuffer
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
-    r: u8,
-    g: u8,
-    b: u8,
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
 }
 
 impl Color {
-    pub const fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Color::new_rgba(r, g, b, 255)
     }
-    pub const fn from_hex(hex: u32) -> Self {
+    // El alfa no lleva codificación sRGB
+    pub fn new_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color {
+            r: decode_channel(r),
+            g: decode_channel(g),
+            b: decode_channel(b),
+            a: a as f32 / 255.0,
+        }
+    }
+    // Valores ya lineales
+    pub const fn from_f32(r: f32, g: f32, b: f32) -> Self {
+        Color { r, g, b, a: 1.0 }
+    }
+    pub fn from_hex(hex: u32) -> Self {
         let r = ((hex >> 16) & 0xFF) as u8;
         let g = ((hex >> 8) & 0xFF) as u8;
         let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
+        Color::new(r, g, b)
     }
     pub const fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color::from_f32(0.0, 0.0, 0.0)
     }
-    pub const fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    // Recorta cada canal a [0, 1] y lo codifica en sRGB; el framebuffer no guarda alfa
+    pub fn to_hex(self) -> u32 {
+        let channel = |value: f32| (linear_to_srgb(value.clamp(0.0, 1.0)) * 255.0).round() as u32;
+        (channel(self.r) << 16) | (channel(self.g) << 8) | channel(self.b)
     }
-    pub fn r(&self) -> u8 {
+    pub fn r(&self) -> f32 {
         self.r
     }
 
-    pub fn g(&self) -> u8 {
+    pub fn g(&self) -> f32 {
         self.g
     }
 
-    pub fn b(&self) -> u8 {
+    pub fn b(&self) -> f32 {
         self.b
     }
 
+    // Opacidad entre 0.0 (transparente) y 1.0 (opaco)
+    pub fn a(&self) -> f32 {
+        self.a
+    }
+
+    // Luminancia relativa (coeficientes Rec. 709 sobre valores lineales)
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Color { a: alpha.clamp(0.0, 1.0), ..self }
+    }
+
+    // Aplica `f` a los tres canales de color, conservando alfa
+    pub fn map(self, f: impl Fn(f32) -> f32) -> Self {
+        Color { r: f(self.r), g: f(self.g), b: f(self.b), a: self.a }
+    }
+
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
         Color {
-            r: (self.r as f32 + (other.r as f32 - self.r as f32) * t).round() as u8,
-            g: (self.g as f32 + (other.g as f32 - self.g as f32) * t).round() as u8,
-            b: (self.b as f32 + (other.b as f32 - self.b as f32) * t).round() as u8,
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
         }
     }
 
     pub fn is_black(&self) -> bool {
-        self.r == 0 && self.g == 0 && self.b == 0
+        self.r == 0.0 && self.g == 0.0 && self.b == 0.0
     }
-    
+
     pub fn blend_normal(&self, blend: &Color) -> Color {
         if blend.is_black() { *self } else { *blend }
     }
-    
+
     pub fn blend_multiply(&self, blend: &Color) -> Color {
-        Color::new(
-            ((self.r as f32 * blend.r as f32) / 255.0) as u8,
-            ((self.g as f32 * blend.g as f32) / 255.0) as u8,
-            ((self.b as f32 * blend.b as f32) / 255.0) as u8
-        )
+        Color {
+            r: self.r * blend.r,
+            g: self.g * blend.g,
+            b: self.b * blend.b,
+            a: self.a,
+        }
     }
-    
+
     pub fn blend_add(&self, blend: &Color) -> Color {
-        Color::new(
-            (self.r as u16 + blend.r as u16).min(255) as u8,
-            (self.g as u16 + blend.g as u16).min(255) as u8,
-            (self.b as u16 + blend.b as u16).min(255) as u8
-        )
+        Color {
+            r: self.r + blend.r,
+            g: self.g + blend.g,
+            b: self.b + blend.b,
+            a: self.a,
+        }
     }
-    
+
     pub fn blend_subtract(&self, blend: &Color) -> Color {
-        Color::new(
-            self.r.saturating_sub(blend.r),
-            self.g.saturating_sub(blend.g),
-            self.b.saturating_sub(blend.b)
-        )
+        Color {
+            r: (self.r - blend.r).max(0.0),
+            g: (self.g - blend.g).max(0.0),
+            b: (self.b - blend.b).max(0.0),
+            a: self.a,
+        }
     }
-    
+
     pub fn blend_screen(&self, blend: &Color) -> Color {
-        Color::new(
-            255 - ((255 - self.r as u16) * (255 - blend.r as u16) / 255) as u8,
-            255 - ((255 - self.g as u16) * (255 - blend.g as u16) / 255) as u8,
-            255 - ((255 - self.b as u16) * (255 - blend.b as u16) / 255) as u8
-        )
-    }    
+        Color {
+            r: 1.0 - (1.0 - self.r) * (1.0 - blend.r),
+            g: 1.0 - (1.0 - self.g) * (1.0 - blend.g),
+            b: 1.0 - (1.0 - self.b) * (1.0 - blend.b),
+            a: self.a,
+        }
+    }
 }
 
 impl Add for Color {
@@ -92,9 +136,10 @@ impl Add for Color {
 
     fn add(self, other: Color) -> Color {
         Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
+            a: self.a,
         }
     }
 }
@@ -105,15 +150,93 @@ impl Mul<f32> for Color {
 
     fn mul(self, scalar: f32) -> Color {
         Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+            r: (self.r * scalar).max(0.0),
+            g: (self.g * scalar).max(0.0),
+            b: (self.b * scalar).max(0.0),
+            a: self.a,
         }
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+        write!(f, "Color(r: {}, g: {}, b: {}, a: {})", self.r, self.g, self.b, self.a)
+    }
+}
+
+// Decodificación sRGB de un canal de 8 bits, con una tabla para no llamar a powf
+// cada vez que un shader crea un color de paleta
+fn decode_channel(value: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    let table = TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)));
+    table[value as usize]
+}
+
+// Curvas de la norma sRGB (IEC 61966-2-1) para valores en [0, 1]
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-5, "{} != {}", actual, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn srgb_decode_matches_reference_values() {
+        assert_close(decode_channel(0), 0.0);
+        assert_close(decode_channel(10), 0.003_035_27);
+        assert_close(decode_channel(64), 0.051_269_46);
+        assert_close(decode_channel(128), 0.215_860_5);
+        assert_close(decode_channel(200), 0.577_580_4);
+        assert_close(decode_channel(255), 1.0);
+    }
+
+    #[test]
+    fn srgb_encode_matches_reference_values() {
+        assert_close(linear_to_srgb(0.0), 0.0);
+        assert_close(linear_to_srgb(0.001), 0.012_92);
+        assert_close(linear_to_srgb(0.18), 0.461_356_3);
+        assert_close(linear_to_srgb(0.5), 0.735_356_7);
+        assert_close(linear_to_srgb(1.0), 1.0);
+    }
+
+    #[test]
+    fn palette_colors_round_trip_through_linear() {
+        for value in 0..=255u32 {
+            let hex = (value << 16) | ((255 - value) << 8) | (value / 2);
+            assert_eq!(Color::from_hex(hex).to_hex(), hex);
+        }
+    }
+
+    #[test]
+    fn shading_math_happens_in_linear_light() {
+        // La mitad de la luz del blanco es 188 en sRGB, no 128
+        let white = Color::new(255, 255, 255);
+        assert_eq!((white * 0.5).to_hex(), 0xBCBCBC);
+        assert_eq!(Color::black().lerp(&white, 0.5).to_hex(), 0xBCBCBC);
+
+        // Rojo y verde puros mezclados al 50% dan (188, 188, 0)
+        let mixed = Color::new(255, 0, 0).lerp(&Color::new(0, 255, 0), 0.5);
+        assert_eq!(mixed.to_hex(), 0xBCBC00);
+
+        // Multiplicar gris 50% sRGB por sí mismo: 0.2158^2 en lineal
+        let gray = Color::new(128, 128, 128);
+        assert_close(gray.blend_multiply(&gray).r(), 0.046_595_7);
+    }
+}
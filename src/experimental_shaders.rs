@@ -1,11 +1,13 @@
-use nalgebra_glm::{Vec2, Vec3};
+// Shaders de prueba que ninguna escena usa; se guardan como referencia para crear otros
+#![allow(dead_code)]
+
 use std::f32::consts::PI;
 use crate::color::Color;
 use crate::Uniforms;
 use rand::prelude::*;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
-use crate::fragment::Fragment;
+use crate::fragment::{Fragment, lambert};
 
 fn static_pattern_shader(fragment: &Fragment) -> Color {
     let x = fragment.vertex_position.x;
@@ -50,9 +52,9 @@ pub fn combined_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
     // Usa el color del círculo si no es negro, sino usa el color base
     if !circle_color.is_black() {
-        circle_color * fragment.intensity
+        circle_color.blend_multiply(&lambert(fragment, uniforms))
     } else {
-        base_color * fragment.intensity
+        base_color.blend_multiply(&lambert(fragment, uniforms))
     }
 }
 
@@ -72,7 +74,7 @@ fn circle_shader(fragment: &Fragment) -> Color {
     }
 }
 
-pub fn combined_blend_shader(fragment: &Fragment, blend_mode: &str) -> Color {
+pub fn combined_blend_shader(fragment: &Fragment, uniforms: &Uniforms, blend_mode: &str) -> Color {
     let base_color = purple_shader(fragment);
     let circle_color = circle_shader(fragment);
 
@@ -84,7 +86,7 @@ pub fn combined_blend_shader(fragment: &Fragment, blend_mode: &str) -> Color {
         _ => base_color
     };
 
-    combined_color * fragment.intensity
+    combined_color.blend_multiply(&lambert(fragment, uniforms))
 }
 
 fn glow_shader(fragment: &Fragment) -> Color {
@@ -128,7 +130,7 @@ pub fn random_color_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let r = rng.gen_range(0..=255);
     let g = rng.gen_range(0..=255);
     let b = rng.gen_range(0..=255);
-    Color::new(r, g, b) * fragment.intensity
+    Color::new(r, g, b).blend_multiply(&lambert(fragment, uniforms))
 }
 
 pub fn panda_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -140,7 +142,7 @@ pub fn panda_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let spot_color = Color::new(255, 255, 255);
     let base_color = Color::new(0, 0, 0);
 
-    (if noise_value < spot_threshold { spot_color } else { base_color }) * fragment.intensity
+    (if noise_value < spot_threshold { spot_color } else { base_color }).blend_multiply(&lambert(fragment, uniforms))
 }
 
 pub fn cloud_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -153,7 +155,7 @@ pub fn cloud_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let cloud_color = Color::new(255, 255, 255);
     let sky_color = Color::new(30, 97, 145);
 
-    (if noise_value > cloud_threshold { cloud_color } else { sky_color }) * fragment.intensity
+    (if noise_value > cloud_threshold { cloud_color } else { sky_color }).blend_multiply(&lambert(fragment, uniforms))
 }
 
 pub fn cellular_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -174,7 +176,7 @@ pub fn cellular_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         cell_color_3
     } else {
         cell_color_4
-    }) * fragment.intensity
+    }).blend_multiply(&lambert(fragment, uniforms))
 }
 
 pub fn get_experimental_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &str) -> Color {
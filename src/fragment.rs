@@ -1,10 +1,8 @@
 use nalgebra_glm::{Vec2, Vec3};
-use std::f32::consts::PI;
 use crate::color::Color;
 use crate::Uniforms;
-use rand::prelude::*;
-use rand::rngs::StdRng;
-use rand::SeedableRng;
+use crate::material::Material;
+use crate::pbr::PbrMaterial;
 use fastnoise_lite::FastNoiseLite;
 
 pub struct Fragment {
@@ -12,23 +10,67 @@ pub struct Fragment {
     pub color: Color,
     pub depth: f32,
     pub normal: Vec3,
-    pub intensity: f32,
-    pub vertex_position: Vec3
+    pub vertex_position: Vec3,
+    pub world_position: Vec3,
+    pub tex_coords: Vec2,
 }
 
 impl Fragment {
-    pub fn new(position: Vec2, color: Color, depth: f32, normal: Vec3, intensity: f32, vertex_position: Vec3) -> Self {
+    pub fn new(position: Vec2, color: Color, depth: f32, normal: Vec3, vertex_position: Vec3) -> Self {
         Fragment {
             position,
             color,
             depth,
             normal,
-            intensity,
-            vertex_position
+            vertex_position,
+            world_position: vertex_position,
+            tex_coords: Vec2::new(0.0, 0.0),
         }
     }
 }
 
+// Fracción de la luz de la estrella que llega al fragmento (1.0 en escenas sin mapa de sombras)
+pub fn shadow_factor(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+    match &uniforms.shadow_map {
+        Some(shadow_map) => shadow_map.factor(&fragment.world_position, &fragment.normal),
+        None => 1.0,
+    }
+}
+
+// Luz difusa (Lambert) que recibe el fragmento sumando todas las luces de la escena
+pub fn lambert(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    uniforms.lights.iter().fold(Color::black(), |total, light| {
+        let (light_dir, radiance) = light.incident(&fragment.world_position);
+        let mut diffuse = fragment.normal.dot(&light_dir).max(0.0);
+        if light.casts_shadows && diffuse > 0.0 {
+            diffuse *= shadow_factor(fragment, uniforms);
+        }
+        total + radiance * diffuse
+    })
+}
+
+// Blinn-Phong con las luces de la escena, vistas desde la cámara
+pub fn blinn_phong(fragment: &Fragment, uniforms: &Uniforms, material: &Material) -> Color {
+    let view_dir = (uniforms.camera_position - fragment.world_position)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or(fragment.normal);
+
+    material.shade(&fragment.normal, &view_dir, &fragment.world_position, &uniforms.lights, |light| {
+        if light.casts_shadows { shadow_factor(fragment, uniforms) } else { 1.0 }
+    })
+}
+
+// Cook-Torrance con las luces de la escena. `ambient` es la luz ambiental que recibe la superficie
+pub fn physically_based(fragment: &Fragment, uniforms: &Uniforms, material: &PbrMaterial, ambient: Color) -> Color {
+    let view_dir = (uniforms.camera_position - fragment.world_position)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or(fragment.normal);
+
+    material.shade(&fragment.normal, &view_dir, &fragment.world_position, &uniforms.lights, ambient, |light| {
+        if light.casts_shadows { shadow_factor(fragment, uniforms) } else { 1.0 }
+    })
+}
+
 // Shaders para planetas
 fn solar_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let x = fragment.vertex_position.x;
@@ -39,11 +81,9 @@ fn solar_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let noise_value = uniforms.noise_open_simplex.get_noise_2d(x * zoom, y * zoom) * 0.3 + 0.7;
     let surface_intensity = (0.9 + noise_value * 0.1) * time_factor;
 
-    let r = (255.0 * surface_intensity) as u8;
-    let g = (200.0 * surface_intensity) as u8;
-    let b = (50.0 * surface_intensity) as u8;
-
-    let core_color = Color::new(r, g, b) * fragment.intensity;
+    // Sin recortar a 255: la superficie puede superar 1.0 y el tone mapping la comprime.
+    // La estrella emite su propia luz, así que no depende de las luces de la escena
+    let core_color = Color::new(255, 200, 50) * surface_intensity;
 
     // Efecto de halo alrededor del Sol
     let distance_to_center = (x.powi(2) + y.powi(2)).sqrt();
@@ -77,8 +117,12 @@ fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let ambient_intensity = 0.7;
     let ambient_color = Color::new(60, 30, 10);
 
-    // Mezcla el color principal con la luz ambiental, eliminando sombras fuertes
-    main_color * fragment.intensity + ambient_color * ambient_intensity
+    // Roca seca: un brillo débil y muy abierto. La luz ambiental elimina las sombras fuertes
+    let material = Material::new(main_color)
+        .with_specular(Color::from_f32(0.04, 0.04, 0.04), 8.0)
+        .with_ambient(ambient_color * ambient_intensity);
+
+    blinn_phong(fragment, uniforms, &material)
 }
 
 fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -99,12 +143,12 @@ fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
     let base_color = Color::new(r, g, b);
 
-    // Ajuste de iluminación ambiental para un aspecto de gas disperso
-    let ambient_intensity = 0.5;
-    let ambient_color = Color::new(80, 130, 200);
+    // Las nubes heladas reflejan la estrella con un brillo concentrado. Sin luz ambiental:
+    // el brillo azulado del borde lo pone la capa de atmósfera
+    let material = Material::new(base_color)
+        .with_specular(Color::from_f32(0.35, 0.4, 0.45), 48.0);
 
-    // Mezcla del color base y el color ambiental para dar una apariencia gaseosa en toda la superficie
-    base_color * fragment.intensity + ambient_color * ambient_intensity
+    blinn_phong(fragment, uniforms, &material)
 }
 
 fn gas_giant_with_rings_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -129,8 +173,11 @@ fn gas_giant_with_rings_shader(fragment: &Fragment, uniforms: &Uniforms) -> Colo
     let ambient_intensity = 0.5;
     let ambient_color = Color::new(90, 60, 120);
 
+    // La sombra de los anillos solo quita la luz directa
+    let direct_light = lambert(fragment, uniforms);
+
     // Mezcla del color base y el color ambiental
-    base_color * fragment.intensity + ambient_color * ambient_intensity
+    base_color.blend_multiply(&direct_light) + ambient_color * ambient_intensity
 }
 
 pub fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -160,7 +207,13 @@ pub fn ring_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let color_intermediate = color1.lerp(&color2, val_normalizado);
     let final_color = color_intermediate.lerp(&color3, val_normalizado);
 
-    final_color * 0.9
+    // Las bandas oscuras son más tenues y dejan ver lo que hay detrás
+    let alpha = 0.85 - val_normalizado * 0.55;
+
+    // Bajo la sombra del planeta los anillos solo conservan una luz tenue
+    let light = 0.25 + 0.75 * shadow_factor(fragment, uniforms);
+
+    (final_color * (0.9 * light)).with_alpha(alpha)
 }
 
 pub fn planet_colorful(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -195,7 +248,7 @@ pub fn planet_colorful(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let ambient_intensity = 0.5;
     let ambient_color = Color::new(90, 60, 120);
 
-    color_final * fragment.intensity + ambient_color * ambient_intensity
+    color_final.blend_multiply(&lambert(fragment, uniforms)) + ambient_color * ambient_intensity
 }
 
 fn ruido_fractal(noise: &FastNoiseLite, x: f32, y: f32, octaves: u32, lacunarity: f32, gain: f32) -> f32 {
@@ -241,7 +294,7 @@ pub fn planet_exotic_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     color_final = color_final.lerp(&color_rosa, patron1 * patron2);
     color_final = color_final.lerp(&color_purpura, (1.0 - patron1) * 0.5);
 
-    color_final * fragment.intensity + ambient_color * ambient_intensity
+    color_final.blend_multiply(&lambert(fragment, uniforms)) + ambient_color * ambient_intensity
 }
 
 pub fn dark_red_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -267,7 +320,7 @@ pub fn dark_red_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color
     let ambient_intensity = 0.7;
     let ambient_color = Color::new(100, 10, 10);
 
-    color_final * fragment.intensity + ambient_color * ambient_intensity
+    color_final.blend_multiply(&lambert(fragment, uniforms)) + ambient_color * ambient_intensity
 }
 
 pub fn rocky_planet_with_moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -297,7 +350,32 @@ pub fn rocky_planet_with_moon_shader(fragment: &Fragment, uniforms: &Uniforms) -
         base_color
     };
 
-    final_color * fragment.intensity
+    // Continentes con ruido en 3D para que los océanos no se repitan en espejo
+    let position = fragment.vertex_position;
+    let continent_zoom = 180.0;
+    let altitude = uniforms.noise_open_simplex.get_noise_3d(
+        position.x * continent_zoom,
+        position.y * continent_zoom,
+        position.z * continent_zoom,
+    );
+    let sea_level = -0.05;
+
+    // Casquetes polares con el borde irregular
+    let ice_line = 0.82 + altitude * 0.08;
+
+    // El agua y el hielo reflejan la estrella con un brillo pequeño e intenso; la roca es mate
+    let material = if position.y.abs() > ice_line {
+        Material::new(Color::new(225, 235, 245))
+            .with_specular(Color::from_f32(0.3, 0.32, 0.35), 64.0)
+    } else if altitude < sea_level {
+        let depth = ((sea_level - altitude) * 2.0).clamp(0.0, 1.0);
+        Material::new(Color::new(30, 90, 130).lerp(&Color::new(10, 30, 70), depth))
+            .with_specular(Color::from_f32(0.6, 0.6, 0.6), 128.0)
+    } else {
+        Material::new(final_color)
+    };
+
+    blinn_phong(fragment, uniforms, &material)
 }
 
 fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -312,11 +390,60 @@ fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let color_sombra = Color::new(20, 20, 20); 
     let color_claro = Color::new(150, 150, 150);
 
-    let factor = (ruido + 1.0) as f32 / 2.0;
+    let factor = (ruido + 1.0) / 2.0;
     let mut color_final = color_base.lerp(&color_sombra, factor * 0.8); 
     color_final = color_final.lerp(&color_claro, factor * 0.5);
 
-    color_final * fragment.intensity
+    color_final.blend_multiply(&lambert(fragment, uniforms))
+}
+
+// Casco de naves y estaciones: paneles metálicos con distinta rugosidad, franjas de
+// pintura dieléctrica y luces de posición emisivas
+fn spacecraft_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let position = fragment.vertex_position;
+
+    // Paneles en una rejilla sobre las coordenadas del modelo
+    let panel_size = 0.25;
+    let panel = (position / panel_size).map(|v| v.floor());
+    let panel_noise = uniforms.noise_cellular.get_noise_3d(panel.x * 13.0, panel.y * 13.0, panel.z * 13.0);
+    let roughness = 0.25 + (panel_noise * 0.5 + 0.5) * 0.45;
+
+    let hull = Color::new(170, 172, 178);
+    let paint = Color::new(180, 40, 30);
+    let stripe = (position.y * 6.0).sin().abs() > 0.97;
+
+    let material = if stripe {
+        PbrMaterial::new(paint, 0.0, 0.5)
+    } else {
+        PbrMaterial::new(hull, 1.0, roughness)
+    };
+
+    // Luces de posición intermitentes cerca del ecuador
+    let beacon = position.y.abs() < 0.03 && (uniforms.time / 30).is_multiple_of(2);
+    let material = if beacon {
+        material.with_emission(Color::from_f32(4.0, 0.4, 0.2))
+    } else {
+        material
+    };
+
+    // Las juntas entre paneles reciben menos luz ambiental
+    let seam = (position / panel_size).map(|v| (v - v.round()).abs()).min() < 0.03;
+    let material = material.with_ambient_occlusion(if seam { 0.4 } else { 1.0 });
+
+    physically_based(fragment, uniforms, &material, Color::from_f32(0.03, 0.03, 0.04))
+}
+
+// Capa exterior translúcida: integra la dispersión a lo largo del rayo desde la cámara,
+// así que no depende de la teselación de la malla. Se mezcla en modo premultiplicado
+fn atmosphere_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let Some(atmosphere) = &uniforms.atmosphere else {
+        return Color::black().with_alpha(0.0);
+    };
+    let Some(view_dir) = (fragment.world_position - uniforms.camera_position).try_normalize(f32::EPSILON) else {
+        return Color::black().with_alpha(0.0);
+    };
+
+    atmosphere.scatter(&uniforms.camera_position, &view_dir)
 }
 
 pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &str) -> Color {
@@ -331,6 +458,8 @@ pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &s
         "dark_red" => dark_red_planet_shader(fragment, uniforms),
         "rocky_planet_with_moon_shader" => rocky_planet_with_moon_shader(fragment, uniforms),
         "moon_shader" => moon_shader(fragment, uniforms),
+        "spacecraft" => spacecraft_shader(fragment, uniforms),
+        "atmosphere" => atmosphere_shader(fragment, uniforms),
         _ => Color::new(0, 0, 0),
     }
 }
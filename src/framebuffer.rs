@@ -1,13 +1,126 @@
-use crate::fragment::{Fragment, fragment_shader};
-use crate::Uniforms;
+use nalgebra_glm::{Vec2, Vec3};
+use crate::fragment::Fragment;
+use crate::color::Color;
+use crate::vertex::Vertex;
+use crate::pipeline::BlendMode;
+use crate::tone_mapping::ToneMapper;
+use crate::parallel::map_pixels;
+
+// Valor de material para los píxeles del G-buffer que no tienen geometría
+pub const NO_MATERIAL: u16 = u16::MAX;
+
+// Atributos de la superficie visible en cada píxel para el sombreado diferido
+pub struct GBuffer {
+    pub normal: Vec<Vec3>,
+    pub vertex_position: Vec<Vec3>,
+    pub world_position: Vec<Vec3>,
+    pub tex_coords: Vec<Vec2>,
+    pub color: Vec<Color>,
+    pub material: Vec<u16>,
+}
+
+impl GBuffer {
+    pub fn new(size: usize) -> Self {
+        GBuffer {
+            normal: vec![Vec3::zeros(); size],
+            vertex_position: vec![Vec3::zeros(); size],
+            world_position: vec![Vec3::zeros(); size],
+            tex_coords: vec![Vec2::zeros(); size],
+            color: vec![Color::black(); size],
+            material: vec![NO_MATERIAL; size],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.material.fill(NO_MATERIAL);
+    }
+
+    pub fn store(&mut self, index: usize, fragment: &Fragment, material: u16) {
+        self.normal[index] = fragment.normal;
+        self.vertex_position[index] = fragment.vertex_position;
+        self.world_position[index] = fragment.world_position;
+        self.tex_coords[index] = fragment.tex_coords;
+        self.color[index] = fragment.color;
+        self.material[index] = material;
+    }
+
+    // Reconstruye el fragmento guardado en el píxel (x, y)
+    pub fn fragment(&self, index: usize, x: usize, y: usize, depth: f32) -> Fragment {
+        Fragment {
+            position: Vec2::new(x as f32, y as f32),
+            color: self.color[index],
+            depth,
+            normal: self.normal[index],
+            vertex_position: self.vertex_position[index],
+            world_position: self.world_position[index],
+            tex_coords: self.tex_coords[index],
+        }
+    }
+
+    // Copia la región (x, y, width, height) de un G-buffer con `stride` columnas
+    pub fn read_region(&self, stride: usize, x: usize, y: usize, width: usize, height: usize) -> GBuffer {
+        GBuffer {
+            normal: read_rows(&self.normal, stride, x, y, width, height),
+            vertex_position: read_rows(&self.vertex_position, stride, x, y, width, height),
+            world_position: read_rows(&self.world_position, stride, x, y, width, height),
+            tex_coords: read_rows(&self.tex_coords, stride, x, y, width, height),
+            color: read_rows(&self.color, stride, x, y, width, height),
+            material: read_rows(&self.material, stride, x, y, width, height),
+        }
+    }
+
+    pub fn write_region(&mut self, stride: usize, x: usize, y: usize, width: usize, region: &GBuffer) {
+        write_rows(&mut self.normal, &region.normal, stride, x, y, width);
+        write_rows(&mut self.vertex_position, &region.vertex_position, stride, x, y, width);
+        write_rows(&mut self.world_position, &region.world_position, stride, x, y, width);
+        write_rows(&mut self.tex_coords, &region.tex_coords, stride, x, y, width);
+        write_rows(&mut self.color, &region.color, stride, x, y, width);
+        write_rows(&mut self.material, &region.material, stride, x, y, width);
+    }
+}
+
+pub fn read_rows<T: Copy>(source: &[T], stride: usize, x: usize, y: usize, width: usize, height: usize) -> Vec<T> {
+    let mut region = Vec::with_capacity(width * height);
+    for row in y..y + height {
+        let start = row * stride + x;
+        region.extend_from_slice(&source[start..start + width]);
+    }
+    region
+}
+
+pub fn write_rows<T: Copy>(target: &mut [T], region: &[T], stride: usize, x: usize, y: usize, width: usize) {
+    for (row, values) in region.chunks(width).enumerate() {
+        let start = (y + row) * stride + x;
+        target[start..start + width].copy_from_slice(values);
+    }
+}
+
+// Triángulos translúcidos (ya en pantalla) que esperan a la pasada transparente
+pub struct TransparentDraw {
+    pub shader_type: String,
+    pub blend_mode: BlendMode,
+    pub triangles: Vec<[Vertex; 3]>,
+}
 
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    // Imagen final para la ventana, producida por tone_map() a partir de `hdr_buffer`
     pub buffer: Vec<u32>,
+    // Color lineal HDR donde escriben todas las pasadas
+    pub hdr_buffer: Vec<Color>,
     pub zbuffer: Vec<f32>,
+    pub gbuffer: Option<GBuffer>,
+    // Con MSAA, color y profundidad por muestra (`samples` consecutivas por píxel)
+    pub samples: usize,
+    pub sample_buffer: Vec<Color>,
+    pub sample_zbuffer: Vec<f32>,
+    pub transparent: Vec<TransparentDraw>,
+    order_independent: bool,
+    materials: Vec<String>,
+    tone_mapper: ToneMapper,
+    exposure: f32,
     background_color: u32,
-    current_color: u32,
 }
 
 impl Framebuffer {
@@ -16,52 +129,225 @@ impl Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
+            hdr_buffer: vec![Color::black(); width * height],
             zbuffer: vec![f32::INFINITY; width * height],
+            gbuffer: None,
+            samples: 1,
+            sample_buffer: Vec::new(),
+            sample_zbuffer: Vec::new(),
+            transparent: Vec::new(),
+            order_independent: false,
+            materials: Vec::new(),
+            tone_mapper: ToneMapper::Clamp,
+            exposure: 1.0,
             background_color: 0x000000,
-            current_color: 0xFFFFFF,
         }
     }
 
     pub fn clear(&mut self) {
-        for pixel in self.buffer.iter_mut() {
-            *pixel = self.background_color;
+        let background = Color::from_hex(self.background_color);
+        for pixel in self.hdr_buffer.iter_mut() {
+            *pixel = background;
         }
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
         }
+        if let Some(gbuffer) = self.gbuffer.as_mut() {
+            gbuffer.clear();
+        }
+        self.sample_buffer.fill(background);
+        self.sample_zbuffer.fill(f32::INFINITY);
+        self.transparent.clear();
+    }
+
+    // Activa MSAA con 2, 4 u 8 muestras por píxel (1 lo desactiva). El G-buffer guarda
+    // una sola superficie por píxel, así que MSAA desactiva el sombreado diferido
+    pub fn set_samples(&mut self, samples: usize) {
+        let samples = if matches!(samples, 2 | 4 | 8) { samples } else { 1 };
+        self.samples = samples;
+
+        if samples > 1 {
+            self.gbuffer = None;
+            self.sample_buffer = vec![Color::from_hex(self.background_color); self.width * self.height * samples];
+            self.sample_zbuffer = vec![f32::INFINITY; self.width * self.height * samples];
+        } else {
+            self.sample_buffer = Vec::new();
+            self.sample_zbuffer = Vec::new();
+        }
+    }
+
+    // Con MSAA, promedia las muestras de cada píxel en `hdr_buffer`. El post-procesado
+    // trabaja sobre ese resultado, antes del tone mapping
+    pub fn resolve_samples(&mut self) {
+        let samples = self.samples;
+        if samples == 1 {
+            return;
+        }
+
+        for (index, pixel) in self.hdr_buffer.iter_mut().enumerate() {
+            let pixel_samples = &self.sample_buffer[index * samples..(index + 1) * samples];
+            let sum = pixel_samples.iter().fold(Color::black(), |sum, &sample| sum + sample);
+            *pixel = sum * (1.0 / samples as f32);
+
+            let pixel_depths = &self.sample_zbuffer[index * samples..(index + 1) * samples];
+            self.zbuffer[index] = pixel_depths.iter().cloned().fold(f32::INFINITY, f32::min);
+        }
+    }
+
+    // Produce `buffer` a partir del color HDR
+    pub fn tone_map(&mut self) {
+        for (pixel, &color) in self.buffer.iter_mut().zip(&self.hdr_buffer) {
+            *pixel = self.tone_mapper.apply(color, self.exposure).to_hex();
+        }
+    }
+
+    pub fn set_tone_mapper(&mut self, tone_mapper: ToneMapper) {
+        self.tone_mapper = tone_mapper;
     }
 
-    pub fn point(&mut self, x: usize, y: usize, depth: f32) {
-        if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+    pub fn tone_mapper(&self) -> ToneMapper {
+        self.tone_mapper
+    }
+
+    // Multiplicador de la luz antes del tone mapping
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure.max(0.0);
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    // Con el sombreado diferido activo, render() solo llena el G-buffer y el color
+    // se calcula una vez por píxel en rasterizer::shade_deferred. Devuelve false si
+    // no se pudo activar: con MSAA el G-buffer no tiene sitio para cada muestra
+    pub fn set_deferred(&mut self, enabled: bool) -> bool {
+        if enabled && self.samples > 1 {
+            return false;
+        }
+        if enabled && self.gbuffer.is_none() {
+            self.gbuffer = Some(GBuffer::new(self.width * self.height));
+        } else if !enabled {
+            self.gbuffer = None;
+        }
+        true
+    }
+
+    pub fn is_deferred(&self) -> bool {
+        self.gbuffer.is_some()
+    }
+
+    // En modo independiente del orden, la pasada transparente guarda una lista de
+    // fragmentos por píxel y la ordena por profundidad antes de mezclar, así que las
+    // superficies que se cruzan se componen bien sin importar el orden de los triángulos
+    pub fn set_order_independent(&mut self, enabled: bool) {
+        self.order_independent = enabled;
+    }
+
+    pub fn is_order_independent(&self) -> bool {
+        self.order_independent
+    }
+
+    // Los triángulos translúcidos se dibujan al final del frame, ordenados de atrás
+    // hacia adelante, en rasterizer::render_transparent
+    pub fn queue_transparent(&mut self, triangles: &[[Vertex; 3]], shader_type: &str, blend_mode: BlendMode) {
+        if triangles.is_empty() {
+            return;
+        }
+        self.transparent.push(TransparentDraw {
+            shader_type: shader_type.to_string(),
+            blend_mode,
+            triangles: triangles.to_vec(),
+        });
+    }
+
+    // Identificador del shader que se guarda en el G-buffer
+    pub fn material_id(&mut self, shader_type: &str) -> u16 {
+        match self.materials.iter().position(|name| name == shader_type) {
+            Some(index) => index as u16,
+            None => {
+                self.materials.push(shader_type.to_string());
+                (self.materials.len() - 1) as u16
             }
         }
     }
 
+    pub fn material_name(&self, material: u16) -> &str {
+        &self.materials[material as usize]
+    }
+
+    // Suma luz al fondo de un píxel, en todas sus muestras con MSAA, sin tocar la profundidad
+    pub fn add_background(&mut self, x: usize, y: usize, color: Color) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let index = y * self.width + x;
+        self.hdr_buffer[index] = self.hdr_buffer[index] + color;
+        for sample in self.sample_buffer.iter_mut().skip(index * self.samples).take(self.samples) {
+            *sample = *sample + color;
+        }
+    }
+
+    // Como `add_background` para todos los píxeles a la vez, con la luz que da `shade(x, y)`.
+    // Con MSAA se evalúa una vez por píxel en `hdr_buffer`, que no se usa hasta
+    // resolve_samples, y se suma a cada muestra
+    pub fn add_background_with<F>(&mut self, shade: F)
+    where
+        F: Fn(usize, usize) -> Color + Sync,
+    {
+        let (width, samples) = (self.width, self.samples);
+        if samples == 1 {
+            map_pixels(&mut self.hdr_buffer, width, |x, y, color| color + shade(x, y));
+            return;
+        }
+
+        map_pixels(&mut self.hdr_buffer, width, |x, y, _| shade(x, y));
+        let background = &self.hdr_buffer;
+        map_pixels(&mut self.sample_buffer, width * samples, |x, y, color| {
+            color + background[y * width + x / samples]
+        });
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deferred_shading_is_refused_with_msaa() {
+        let mut framebuffer = Framebuffer::new(8, 8);
+        assert!(framebuffer.set_deferred(true));
+
+        framebuffer.set_samples(4);
+        assert!(!framebuffer.is_deferred());
+        assert!(!framebuffer.set_deferred(true));
+        assert!(framebuffer.set_deferred(false));
 
-    pub fn set_current_color(&mut self, color: u32) {
-        self.current_color = color;
+        framebuffer.set_samples(1);
+        assert!(framebuffer.set_deferred(true) && framebuffer.is_deferred());
     }
 
-    pub fn process_fragments(&mut self, fragments: &[Fragment], uniforms: &Uniforms) {
-        for fragment in fragments {
-            let x = fragment.position.x as usize;
-            let y = fragment.position.y as usize;
-            if x < self.width && y < self.height {
-                // Llamada al fragment shader para calcular el color ajustado
-                let shaded_color = fragment_shader(&fragment, uniforms, "static_pattern");
-                let color = shaded_color.to_hex();
-                
-                // Configura el color actual del framebuffer y dibuja el punto
-                self.set_current_color(color);
-                self.point(x, y, fragment.depth);
-            }
+    #[test]
+    fn background_pass_reaches_every_msaa_sample() {
+        let mut framebuffer = Framebuffer::new(5, 3);
+        framebuffer.set_background_color(0x000000);
+        framebuffer.set_samples(4);
+        framebuffer.clear();
+
+        let light = |x: usize, y: usize| Color::from_f32(x as f32, y as f32, 1.0);
+        framebuffer.add_background_with(light);
+        framebuffer.resolve_samples();
+
+        for (index, sample) in framebuffer.sample_buffer.iter().enumerate() {
+            let pixel = index / 4;
+            assert_eq!(*sample, light(pixel % 5, pixel / 5));
+        }
+        for (index, pixel) in framebuffer.hdr_buffer.iter().enumerate() {
+            assert_eq!(*pixel, light(index % 5, index / 5));
         }
     }
-}
\ No newline at end of file
+}
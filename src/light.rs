@@ -0,0 +1,144 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+
+// Tipos de fuente de luz; las posiciones y direcciones están en espacio de mundo
+#[derive(Debug, Clone, Copy)]
+pub enum LightKind {
+    // Luz muy lejana: todos los rayos llegan paralelos, en sentido `direction`
+    Directional { direction: Vec3 },
+    Point { position: Vec3 },
+    // Cono alrededor de `direction`; entre los dos ángulos (en radianes) la luz se desvanece
+    Spot { position: Vec3, direction: Vec3, inner_angle: f32, outer_angle: f32 },
+}
+
+// Atenuación con la distancia d: 1 / (constant + linear * d + quadratic * d^2)
+#[derive(Debug, Clone, Copy)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    // Sin pérdida con la distancia
+    pub const NONE: Attenuation = Attenuation { constant: 1.0, linear: 0.0, quadratic: 0.0 };
+
+    pub fn factor(&self, distance: f32) -> f32 {
+        1.0 / (self.constant + self.linear * distance + self.quadratic * distance * distance).max(1e-4)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+    pub attenuation: Attenuation,
+    // Si es true, el mapa de sombras de Uniforms oculta esta luz
+    pub casts_shadows: bool,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Color, intensity: f32) -> Self {
+        Light::new(LightKind::Directional { direction: direction.normalize() }, color, intensity)
+    }
+
+    pub fn point(position: Vec3, color: Color, intensity: f32) -> Self {
+        Light::new(LightKind::Point { position }, color, intensity)
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, inner_angle: f32, outer_angle: f32, color: Color, intensity: f32) -> Self {
+        Light::new(
+            LightKind::Spot { position, direction: direction.normalize(), inner_angle, outer_angle },
+            color,
+            intensity,
+        )
+    }
+
+    fn new(kind: LightKind, color: Color, intensity: f32) -> Self {
+        Light { kind, color, intensity, attenuation: Attenuation::NONE, casts_shadows: false }
+    }
+
+    pub fn with_attenuation(self, constant: f32, linear: f32, quadratic: f32) -> Self {
+        Light { attenuation: Attenuation { constant, linear, quadratic }, ..self }
+    }
+
+    pub fn with_shadows(self) -> Self {
+        Light { casts_shadows: true, ..self }
+    }
+
+    // Dirección normalizada desde `world_position` hacia la luz y la luz que llega a
+    // ese punto (color por intensidad, atenuación y cono)
+    pub fn incident(&self, world_position: &Vec3) -> (Vec3, Color) {
+        match self.kind {
+            LightKind::Directional { direction } => (-direction, self.color * self.intensity),
+            LightKind::Point { position } => {
+                let (to_light, distance) = direction_and_distance(world_position, &position);
+                (to_light, self.color * (self.intensity * self.attenuation.factor(distance)))
+            },
+            LightKind::Spot { position, direction, inner_angle, outer_angle } => {
+                let (to_light, distance) = direction_and_distance(world_position, &position);
+                let (cos_inner, cos_outer) = (inner_angle.cos(), outer_angle.cos());
+                let cos_angle = (-to_light).dot(&direction);
+                let cone = ((cos_angle - cos_outer) / (cos_inner - cos_outer).max(1e-4)).clamp(0.0, 1.0);
+                let cone = cone * cone * (3.0 - 2.0 * cone);
+                (to_light, self.color * (self.intensity * self.attenuation.factor(distance) * cone))
+            },
+        }
+    }
+}
+
+fn direction_and_distance(from: &Vec3, to: &Vec3) -> (Vec3, f32) {
+    let offset = to - from;
+    let distance = offset.magnitude();
+    if distance > 0.0 {
+        (offset / distance, distance)
+    } else {
+        (Vec3::zeros(), 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const WHITE: Color = Color::from_f32(1.0, 1.0, 1.0);
+
+    #[test]
+    fn directional_light_is_the_same_everywhere() {
+        let light = Light::directional(Vec3::new(0.0, -2.0, 0.0), WHITE, 0.5);
+        for position in [Vec3::zeros(), Vec3::new(40.0, -3.0, 7.0)] {
+            let (to_light, radiance) = light.incident(&position);
+            assert!((to_light - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-6);
+            assert!((radiance.r() - 0.5).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn point_light_follows_its_attenuation() {
+        let light = Light::point(Vec3::new(0.0, 3.0, 0.0), WHITE, 2.0).with_attenuation(1.0, 0.0, 1.0);
+        let (to_light, radiance) = light.incident(&Vec3::zeros());
+
+        assert!((to_light - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-6);
+        // 2 / (1 + 0 * 3 + 1 * 3^2)
+        assert!((radiance.r() - 0.2).abs() < 1e-6, "{}", radiance.r());
+        assert_eq!(Attenuation::NONE.factor(100.0), 1.0);
+    }
+
+    #[test]
+    fn spot_light_fades_between_its_cone_angles() {
+        let light = Light::spot(Vec3::zeros(), Vec3::new(0.0, 0.0, -1.0), PI / 12.0, PI / 6.0, WHITE, 1.0);
+        // Punto a distancia 1 del foco, a `angle` radianes del eje del cono
+        let radiance_at = |angle: f32| light.incident(&Vec3::new(angle.sin(), 0.0, -angle.cos())).1.r();
+
+        assert!((radiance_at(0.0) - 1.0).abs() < 1e-5);
+        assert!((radiance_at(PI / 13.0) - 1.0).abs() < 1e-5);
+        assert_eq!(radiance_at(PI / 5.0), 0.0);
+
+        let edges = [PI / 10.0, PI / 8.0, PI / 7.0];
+        let falloff: Vec<f32> = edges.iter().map(|&angle| radiance_at(angle)).collect();
+        assert!(falloff.iter().all(|&radiance| radiance > 0.0 && radiance < 1.0), "{:?}", falloff);
+        assert!(falloff.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", falloff);
+    }
+}
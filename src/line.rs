@@ -1,54 +1,169 @@
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::color::Color;
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::Vec2;
 
-pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+#[derive(Debug, Clone, Copy)]
+pub struct LineStyle {
+    // Grosor en píxeles, medido perpendicular a la línea
+    pub thickness: f32,
+    pub anti_aliased: bool,
+}
 
+impl LineStyle {
+    pub fn new(thickness: f32, anti_aliased: bool) -> Self {
+        LineStyle { thickness, anti_aliased }
+    }
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle::new(1.0, false)
+    }
+}
+
+// Recorre el eje mayor de la línea un píxel a la vez y en el eje menor cubre un tramo
+// del grosor pedido. Con antialiasing, los píxeles del borde del tramo reciben una
+// cobertura parcial como en el algoritmo de Xiaolin Wu; `emit` recibe esa cobertura
+// (1.0 sin antialiasing)
+pub fn line(a: &Vertex, b: &Vertex, style: &LineStyle, emit: &mut impl FnMut(Fragment, f32)) {
     let start = a.transformed_position;
     let end = b.transformed_position;
+    let steep = (end.y - start.y).abs() > (end.x - start.x).abs();
 
-    let mut x0 = start.x as i32;
-    let mut y0 = start.y as i32;
-    let x1 = end.x as i32;
-    let y1 = end.y as i32;
+    // Coordenadas en (eje mayor, eje menor)
+    let (major0, minor0, major1, minor1) = if steep {
+        (start.y, start.x, end.y, end.x)
+    } else {
+        (start.x, start.y, end.x, end.y)
+    };
+    let length = major1 - major0;
+    let slope = if length != 0.0 { (minor1 - minor0) / length } else { 0.0 };
 
-    let dx = (x1 - x0).abs();
-    let dy = (y1 - y0).abs();
+    // Alto del tramo en el eje menor para mantener el grosor perpendicular
+    let span = style.thickness * (1.0 + slope * slope).sqrt();
 
-    let sx = if x0 < x1 { 1 } else { -1 };
-    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut first = (major0.min(major1) - 0.5).ceil() as i32;
+    let mut last = (major0.max(major1) - 0.5).floor() as i32;
+    if first > last {
+        // Línea más corta que un píxel
+        first = ((major0 + major1) * 0.5).floor() as i32;
+        last = first;
+    }
 
-    let mut err = if dx > dy { dx / 2 } else { -dy / 2 };
+    for major in first..=last {
+        let t = if length != 0.0 {
+            ((major as f32 + 0.5 - major0) / length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let minor = minor0 + (minor1 - minor0) * t;
+        let (low, high) = (minor - span * 0.5, minor + span * 0.5);
 
-    let normal_vector = Vec3::new(0.0, 0.0, 1.0);
-    let intensity_value = 1.0;
+        let mut plot = |minor_pixel: i32, coverage: f32| {
+            let (x, y) = if steep { (minor_pixel, major) } else { (major, minor_pixel) };
+            emit(line_fragment(a, b, t, x, y), coverage);
+        };
 
-    loop {
-        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x) as f32;
-        
-        fragments.push(Fragment::new(
-            Vec2::new(x0 as f32, y0 as f32),
-            Color::new(255, 255, 255),
-            z,
-            normal_vector,
-            intensity_value ,
-            start
-        ));
+        if style.anti_aliased {
+            for minor_pixel in low.floor() as i32..=high.floor() as i32 {
+                let pixel = minor_pixel as f32;
+                let coverage = (high.min(pixel + 1.0) - low.max(pixel)).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    plot(minor_pixel, coverage);
+                }
+            }
+        } else {
+            // Píxeles cuyo centro cae dentro del tramo, al menos uno
+            let mut low_pixel = (low - 0.5).ceil() as i32;
+            let mut high_pixel = (high - 0.5).floor() as i32;
+            if low_pixel > high_pixel {
+                low_pixel = minor.floor() as i32;
+                high_pixel = low_pixel;
+            }
+            for minor_pixel in low_pixel..=high_pixel {
+                plot(minor_pixel, 1.0);
+            }
+        }
+    }
+}
 
-        if x0 == x1 && y0 == y1 { break; }
+// Interpola la profundidad linealmente en pantalla y el resto de atributos con
+// corrección de perspectiva según el parámetro `t` de la línea
+fn line_fragment(a: &Vertex, b: &Vertex, t: f32, x: i32, y: i32) -> Fragment {
+    let depth = a.transformed_position.z + (b.transformed_position.z - a.transformed_position.z) * t;
 
-        let e2 = err;
-        if e2 > -dx {
-            err -= dy;
-            x0 += sx;
-        }
-        if e2 < dy {
-            err += dx;
-            y0 += sy;
+    let weight_a = (1.0 - t) / a.clip_position.w;
+    let weight_b = t / b.clip_position.w;
+    let t = weight_b / (weight_a + weight_b);
+
+    Fragment {
+        position: Vec2::new(x as f32, y as f32),
+        color: a.color.lerp(&b.color, t),
+        depth,
+        normal: a.transformed_normal.lerp(&b.transformed_normal, t),
+        vertex_position: a.position.lerp(&b.position, t),
+        world_position: a.world_position.lerp(&b.world_position, t),
+        tex_coords: a.tex_coords.lerp(&b.tex_coords, t),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec3;
+    use std::collections::BTreeMap;
+
+    fn screen_vertex(x: f32, y: f32, depth: f32) -> Vertex {
+        Vertex::new(Vec3::new(x, y, depth), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0))
+    }
+
+    fn fragments(a: &Vertex, b: &Vertex, style: &LineStyle) -> Vec<(Fragment, f32)> {
+        let mut out = Vec::new();
+        line(a, b, style, &mut |fragment, coverage| out.push((fragment, coverage)));
+        out
+    }
+
+    #[test]
+    fn vertical_line_has_finite_depth_and_reaches_both_ends() {
+        let (a, b) = (screen_vertex(10.5, 2.5, 0.2), screen_vertex(10.5, 12.5, 0.8));
+        let out = fragments(&a, &b, &LineStyle::default());
+
+        assert_eq!(out.len(), 11);
+        assert!(out.iter().all(|(fragment, _)| fragment.position.x == 10.0 && fragment.depth.is_finite()));
+
+        let (first, last) = (&out[0].0, &out[out.len() - 1].0);
+        assert_eq!((first.position.y, last.position.y), (2.0, 12.0));
+        assert!((first.depth - 0.2).abs() < 1e-5 && (last.depth - 0.8).abs() < 1e-5);
+        // Los atributos del extremo final vienen de `b`, no de `a`
+        assert!((last.vertex_position - b.position).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn depth_follows_the_line_parameter() {
+        let (a, b) = (screen_vertex(0.0, 5.5, 0.0), screen_vertex(20.0, 5.5, 1.0));
+        for (fragment, _) in fragments(&a, &b, &LineStyle::default()) {
+            let t = (fragment.position.x + 0.5) / 20.0;
+            assert!((fragment.depth - t).abs() < 1e-5, "x {}: {}", fragment.position.x, fragment.depth);
         }
     }
 
-    fragments
-}
\ No newline at end of file
+    #[test]
+    fn thick_lines_cover_their_width_in_pixels() {
+        let (a, b) = (screen_vertex(2.0, 10.5, 0.5), screen_vertex(30.0, 10.5, 0.5));
+
+        let mut columns: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+        for (fragment, coverage) in fragments(&a, &b, &LineStyle::new(3.0, false)) {
+            assert_eq!(coverage, 1.0);
+            columns.entry(fragment.position.x as i32).or_default().push(fragment.position.y as i32);
+        }
+        assert_eq!(columns.len(), 28);
+        assert!(columns.values().all(|rows| rows == &vec![9, 10, 11]));
+
+        // Con antialiasing la cobertura de cada columna suma el grosor
+        let mut coverage_per_column: BTreeMap<i32, f32> = BTreeMap::new();
+        for (fragment, coverage) in fragments(&a, &b, &LineStyle::new(2.5, true)) {
+            *coverage_per_column.entry(fragment.position.x as i32).or_default() += coverage;
+        }
+        assert!(coverage_per_column.values().all(|&total| (total - 2.5).abs() < 1e-4));
+    }
+}
@@ -1,5 +1,5 @@
-use nalgebra_glm::{Vec2, Vec3, Mat4};
-use minifb::{Key, Window, WindowOptions};
+use nalgebra_glm::{Vec3, Mat4};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use std::time::Duration;
 use std::f32::consts::PI;
 
@@ -12,14 +12,36 @@ mod color;
 mod fragment;
 mod shaders;
 mod experimental_shaders;
+mod camera;
+mod clipping;
+mod pipeline;
+mod rasterizer;
+mod parallel;
+mod tone_mapping;
+mod post_process;
+mod shadow;
+mod light;
+mod material;
+mod pbr;
+mod atmosphere;
+mod skybox;
 
 use framebuffer::Framebuffer;
 use vertex::Vertex;
-use obj::Obj;
-use triangle::triangle;
+use obj::{Obj, IndexedMesh};
 use shaders::vertex_shader;
+use camera::Camera;
+use clipping::{clip_triangle, clip_line, to_screen};
+use pipeline::{RenderState, FillMode, BlendMode};
+use tone_mapping::ToneMapper;
+use post_process::{PostProcess, PostEffect};
+use shadow::ShadowMap;
+use light::Light;
+use atmosphere::Atmosphere;
+use skybox::{Starfield, Nebula, NebulaPalette};
+use rasterizer::{rasterize, rasterize_wireframe, rasterize_points, rasterize_lines, shade_deferred, render_transparent};
+use line::LineStyle;
 use fastnoise_lite::{FastNoiseLite, NoiseType, CellularDistanceFunction};
-use crate::fragment::{fragment_shader, Fragment, ring_shader};
 use crate::color::Color;
 
 pub struct Uniforms {
@@ -30,6 +52,32 @@ pub struct Uniforms {
     time: u32,
     noise_open_simplex: FastNoiseLite,
     noise_cellular: FastNoiseLite, 
+    // Solo en las escenas donde un cuerpo puede hacer sombra sobre otro
+    shadow_map: Option<ShadowMap>,
+    // Atmósfera del planeta de la escena, que dibuja el shader "atmosphere"
+    atmosphere: Option<Atmosphere>,
+    lights: Vec<Light>,
+    // Posición de la cámara en el mundo, para la dirección de vista de los brillos
+    camera_position: Vec3,
+}
+
+// Dónde está la estrella del sistema cuando la escena muestra otro cuerpo
+pub const STAR_POSITION: Vec3 = Vec3::new(-12.0, 2.0, 0.0);
+const STAR_SCALE: f32 = 1.5;
+// Luz blanca cálida que emite la estrella, en valores lineales
+pub const STAR_EMISSION: Color = Color::from_f32(1.0, 0.95, 0.88);
+
+pub struct Star {
+    pub position: Vec3,
+    pub scale: f32,
+    pub emission: Color,
+}
+
+// Toda estrella dibujada es una luz puntual: la posición sale de su transformación y el
+// color de lo que emite. Es la única luz que el mapa de sombras tiene en cuenta
+fn star_light(model_matrix: &Mat4, emission: Color) -> Light {
+    let position = Vec3::new(model_matrix[(0, 3)], model_matrix[(1, 3)], model_matrix[(2, 3)]);
+    Light::point(position, emission, 1.0).with_shadows()
 }
 
 pub struct Moon {
@@ -39,12 +87,8 @@ pub struct Moon {
 }
 
 fn create_uniforms() -> Uniforms {
-    let mut noise_open_simplex = FastNoiseLite::with_seed(1337);
-    noise_open_simplex.set_noise_type(Some(NoiseType::OpenSimplex2));
-    
-    let mut noise_cellular = FastNoiseLite::with_seed(1337);
-    noise_cellular.set_noise_type(Some(NoiseType::Cellular));
-    noise_cellular.set_cellular_distance_function(Some(CellularDistanceFunction::Manhattan));
+    let noise_open_simplex = create_open_simplex_noise();
+    let noise_cellular = create_cellular_noise();
 
     Uniforms {
         model_matrix: Mat4::identity(),
@@ -54,6 +98,10 @@ fn create_uniforms() -> Uniforms {
         time: 0,
         noise_open_simplex,
         noise_cellular,
+        shadow_map: None,
+        atmosphere: None,
+        lights: Vec::new(),
+        camera_position: Vec3::new(0.0, 0.0, 0.0),
     }
 }
 
@@ -95,41 +143,94 @@ fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
     transform_matrix * rotation_matrix
 }
 
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], shader_type: &str) {
-    // Vertex Shader Stage
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
-    }
+fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
+    Mat4::new(
+        width / 2.0, 0.0,           0.0, width / 2.0,
+        0.0,         -height / 2.0, 0.0, height / 2.0,
+        0.0,         0.0,           0.5, 0.5,
+        0.0,         0.0,           0.0, 1.0,
+    )
+}
 
+fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, mesh: &IndexedMesh, shader_type: &str, render_state: &RenderState) {
+    // Vertex Shader Stage: cada vértice único se transforma una sola vez y el
+    // resultado queda en caché para todos los triángulos que lo comparten
+    let transformed_vertices: Vec<Vertex> = mesh.vertices.iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
+
+    let render_state = render_state.for_model(&uniforms.model_matrix);
+
+    // Primitive Assembly a partir del index buffer y recorte contra el frustum
     let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+    for indices in mesh.indices.chunks_exact(3) {
+        let clipped = clip_triangle(
+            &transformed_vertices[indices[0] as usize],
+            &transformed_vertices[indices[1] as usize],
+            &transformed_vertices[indices[2] as usize],
+        );
+        for tri in clipped {
+            let tri = tri.map(|v| to_screen(&v, &uniforms.viewport_matrix));
+            if !render_state.is_culled(&tri[0], &tri[1], &tri[2]) {
+                triangles.push(tri);
+            }
         }
     }
 
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
+    // Rasterización y Fragment Shader por tiles en paralelo. Las mallas translúcidas
+    // se encolan para la pasada transparente al final del frame
+    let solid = |framebuffer: &mut Framebuffer| {
+        if render_state.blend_mode.is_transparent() {
+            framebuffer.queue_transparent(&triangles, shader_type, render_state.blend_mode);
+        } else {
+            rasterize(framebuffer, &triangles, uniforms, shader_type);
+        }
+    };
+
+    match render_state.fill_mode {
+        FillMode::Solid => solid(framebuffer),
+        FillMode::Wireframe => rasterize_wireframe(framebuffer, &triangles),
+        FillMode::SolidWireframe => {
+            solid(framebuffer);
+            rasterize_wireframe(framebuffer, &triangles);
+        },
+        FillMode::Points => rasterize_points(framebuffer, &triangles),
     }
+}
+
+// Dibuja la capa de la atmósfera con la malla de la esfera. La capa es algo mayor que la
+// atmósfera para que las facetas de la malla no recorten el halo en la silueta
+fn render_atmosphere(framebuffer: &mut Framebuffer, uniforms: &mut Uniforms, mesh: &IndexedMesh, atmosphere: Atmosphere, render_state: &RenderState) {
+    let shell_scale = atmosphere.atmosphere_radius * 1.02 / mesh.bounding_radius();
+    uniforms.model_matrix = create_model_matrix(atmosphere.center, shell_scale, Vec3::zeros());
+    uniforms.atmosphere = Some(atmosphere);
+    render(framebuffer, uniforms, mesh, "atmosphere", render_state);
+}
 
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = fragment_shader(&fragment, uniforms, shader_type);
-            let color = shaded_color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
+// Dibuja una lista de segmentos (pares de vértices) en el espacio del modelo
+fn render_lines(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], style: &LineStyle) {
+    let mut lines = Vec::with_capacity(vertex_array.len() / 2);
+    for segment in vertex_array.chunks_exact(2) {
+        let a = vertex_shader(&segment[0], uniforms);
+        let b = vertex_shader(&segment[1], uniforms);
+        if let Some(clipped) = clip_line(&a, &b) {
+            lines.push(clipped.map(|v| to_screen(&v, &uniforms.viewport_matrix)));
         }
     }
-    
+
+    rasterize_lines(framebuffer, &lines, style);
+}
+
+// Circunferencia en el plano XY como lista de segmentos
+fn create_orbit_path(center: Vec3, radius: f32, segments: usize, color: Color) -> Vec<Vertex> {
+    let mut vertices = Vec::with_capacity(segments * 2);
+    for i in 0..segments {
+        let start = i as f32 / segments as f32 * 2.0 * PI;
+        let end = (i + 1) as f32 / segments as f32 * 2.0 * PI;
+        vertices.push(Vertex::new_with_color(center + Vec3::new(radius * start.cos(), radius * start.sin(), 0.0), color));
+        vertices.push(Vertex::new_with_color(center + Vec3::new(radius * end.cos(), radius * end.sin(), 0.0), color));
+    }
+    vertices
 }
 
 fn create_open_simplex_noise() -> FastNoiseLite {
@@ -141,6 +242,7 @@ fn create_open_simplex_noise() -> FastNoiseLite {
 fn create_cellular_noise() -> FastNoiseLite {
     let mut noise = FastNoiseLite::with_seed(1337);
     noise.set_noise_type(Some(NoiseType::Cellular));
+    noise.set_cellular_distance_function(Some(CellularDistanceFunction::Manhattan));
     noise
 }
 
@@ -163,17 +265,38 @@ fn main() {
     window.set_position(500, 500);
     window.update();
 
-    framebuffer.set_background_color(0x333355);
+    // Casi negro: el fondo lo llenan las estrellas
+    framebuffer.set_background_color(0x020308);
+    framebuffer.set_samples(parse_msaa_samples());
+    if !framebuffer.set_deferred(true) {
+        eprintln!("Con MSAA ({} muestras) se usa el sombreado directo", framebuffer.samples);
+    }
+    window.set_title(shading_title(&framebuffer));
+    framebuffer.set_order_independent(true);
+    framebuffer.set_tone_mapper(ToneMapper::Aces);
 
-    let mut translation = Vec3::new(window_width as f32 / 2.0, window_height as f32 / 2.0, 0.0);
+    let translation = Vec3::new(0.0, 0.0, 0.0);
     let mut rotation = Vec3::new(0.0, 0.0, 0.0);
-    let mut scale = 100.0f32;
+    let scale = 1.0f32;
+
+    let mut camera = Camera::new(
+        Vec3::new(0.0, 1.0, 7.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
 
     let planet_obj = Obj::load("assets/sphere.obj").expect("Failed to load obj");
-    let planet_vertex_array = planet_obj.get_vertex_array();
+    let planet_mesh = planet_obj.get_indexed_mesh();
 
     let ring_obj = Obj::load("assets/rings.obj").expect("Failed to load rings.obj");
-    let ring_vertex_array = ring_obj.get_vertex_array(); 
+    let ring_mesh = ring_obj.get_indexed_mesh(); 
+
+    let mut planet_render_state = RenderState::default();
+    let mut ring_render_state = RenderState::transparent(BlendMode::Alpha);
+    // La atmósfera integra todo el rayo en su cara frontal, así que la trasera se descarta
+    let mut atmosphere_render_state = RenderState { blend_mode: BlendMode::Premultiplied, ..RenderState::default() };
+    let planet_radius = planet_mesh.bounding_radius() * scale;
 
     let mut time = 0;
 
@@ -186,13 +309,43 @@ fn main() {
     const PLANET_EXOTIC: u8 = 6;
     const DARK_RED: u8 = 7;
     const ROCKY_PLANET_WITH_MOON: u8 = 8;
+    const METAL_SPHERE: u8 = 9;
 
     // Variable para guardar el cuerpo celeste seleccionado
     let mut selected_object: u8 = STAR;
 
+    let moon_orbit_radius = 1.5;
+    let moon_orbit_path = create_orbit_path(translation, moon_orbit_radius, 128, Color::new(150, 150, 200));
+    let orbit_style = LineStyle::new(1.5, true);
+
+    const SHADOW_MAP_SIZE: usize = 1024;
+
+    // Post-procesado por escena: la estrella tiene un bloom amplio para brillar fuera de su silueta
+    let star_post_process = PostProcess::new(vec![
+        PostEffect::Bloom { threshold: 0.8, intensity: 1.2, radius: 14.0 },
+        PostEffect::Vignette { strength: 0.5, radius: 0.4 },
+    ]);
+    let moon_post_process = PostProcess::new(vec![
+        PostEffect::Bloom { threshold: 1.2, intensity: 0.5, radius: 6.0 },
+        PostEffect::ChromaticAberration { offset: 3.0 },
+        PostEffect::Vignette { strength: 0.6, radius: 0.3 },
+        PostEffect::FilmGrain { amount: 0.06 },
+    ]);
+    let planet_post_process = PostProcess::new(vec![
+        PostEffect::Bloom { threshold: 1.2, intensity: 0.5, radius: 6.0 },
+        PostEffect::Vignette { strength: 0.5, radius: 0.4 },
+        PostEffect::FilmGrain { amount: 0.03 },
+    ]);
+    let mut post_processing = true;
+
+    let starfield = Starfield::new(1337, 8000);
+    let mut nebula = Nebula::new(1337, NebulaPalette::EMISSION, 0.45);
+    let nebula_palettes = [NebulaPalette::EMISSION, NebulaPalette::REFLECTION];
+    let mut nebula_palette = 0;
+
     let mut moon = Moon {
         position: Vec3::new(0.0, 0.0, 0.0),
-        scale: 30.0,
+        scale: 0.3,
         rotation: Vec3::new(0.0, 0.0, 0.0),
     };
 
@@ -202,7 +355,7 @@ fn main() {
         }
         time += 1;
 
-        handle_input(&window, &mut translation, &mut rotation, &mut scale);
+        handle_input(&window, &mut camera, &mut rotation);
 
         // Cambiamos el objeto seleccionado con teclas
         if window.is_key_down(Key::Key1) {
@@ -221,6 +374,54 @@ fn main() {
             selected_object = DARK_RED;
         } else if window.is_key_down(Key::Key8) {
             selected_object = ROCKY_PLANET_WITH_MOON;
+        } else if window.is_key_down(Key::Key9) {
+            selected_object = METAL_SPHERE;
+        }
+
+        // G alterna entre sombreado diferido y directo. Con MSAA se queda en directo y el
+        // título de la ventana lo indica
+        if window.is_key_pressed(Key::G, KeyRepeat::No) {
+            let deferred = !framebuffer.is_deferred();
+            framebuffer.set_deferred(deferred);
+            window.set_title(shading_title(&framebuffer));
+        }
+
+        // T alterna entre transparencia independiente del orden y triángulos ordenados
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            let order_independent = !framebuffer.is_order_independent();
+            framebuffer.set_order_independent(order_independent);
+        }
+
+        // M cambia el tone mapping; - y = ajustan la exposición
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            let tone_mapper = framebuffer.tone_mapper().next();
+            framebuffer.set_tone_mapper(tone_mapper);
+        }
+        if window.is_key_down(Key::Minus) {
+            let exposure = framebuffer.exposure() * 0.97;
+            framebuffer.set_exposure(exposure);
+        }
+        if window.is_key_down(Key::Equal) {
+            let exposure = framebuffer.exposure() * 1.03;
+            framebuffer.set_exposure(exposure);
+        }
+
+        // N cambia la paleta de las nebulosas; el cubemap de ruido no se recalcula
+        if window.is_key_pressed(Key::N, KeyRepeat::No) {
+            nebula_palette = (nebula_palette + 1) % nebula_palettes.len();
+            nebula.palette = nebula_palettes[nebula_palette];
+        }
+
+        // P activa o desactiva el post-procesado
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            post_processing = !post_processing;
+        }
+
+        // F cambia el modo de relleno: sólido, alambre, sólido con alambre y puntos
+        if window.is_key_pressed(Key::F, KeyRepeat::No) {
+            planet_render_state.fill_mode = planet_render_state.fill_mode.next();
+            ring_render_state.fill_mode = planet_render_state.fill_mode;
+            atmosphere_render_state.fill_mode = planet_render_state.fill_mode;
         }
 
         framebuffer.clear();
@@ -228,66 +429,139 @@ fn main() {
         let model_matrix = create_model_matrix(translation, scale, rotation);
         let mut uniforms = create_uniforms();
         uniforms.model_matrix = model_matrix;
+        uniforms.view_matrix = camera.view_matrix();
+        uniforms.camera_position = camera.eye;
+        uniforms.projection_matrix = camera.projection_matrix(framebuffer_width as f32, framebuffer_height as f32);
+        uniforms.viewport_matrix = viewport_matrix;
         uniforms.time = time;
 
+        // El cielo va primero y sin profundidad: todo lo demás se dibuja por delante
+        nebula.render(&mut framebuffer, &uniforms);
+        starfield.render(&mut framebuffer, &uniforms);
+
+        // La estrella se dibuja en todas las escenas y de ella sale la luz. En su propia
+        // escena ocupa el centro; en las demás está lejos, a un lado del sistema
+        let star = if selected_object == STAR {
+            Star { position: translation, scale, emission: STAR_EMISSION }
+        } else {
+            Star { position: STAR_POSITION, scale: STAR_SCALE, emission: STAR_EMISSION }
+        };
+        let star_model_matrix = create_model_matrix(star.position, star.scale, rotation);
+        uniforms.lights = vec![star_light(&star_model_matrix, star.emission)];
+        uniforms.model_matrix = star_model_matrix;
+        render(&mut framebuffer, &uniforms, &planet_mesh, "solar_surface", &planet_render_state);
+        uniforms.model_matrix = model_matrix;
+
         // Renderizamos el objeto seleccionado con shaders específicos
         match selected_object {
-            STAR => {
-                framebuffer.set_current_color(0xFFDDDD);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "solar_surface");
-            },
+            // Ya dibujada arriba
+            STAR => {},
             ROCKY_PLANET => {
-                framebuffer.set_current_color(0xAAAAAA);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "rocky_planet_shader");
+                render(&mut framebuffer, &uniforms, &planet_mesh, "rocky_planet_shader", &planet_render_state);
+
+                let atmosphere = Atmosphere::new(translation, planet_radius, star.position - translation)
+                    .with_sun_radiance(star.emission * 10.0);
+                render_atmosphere(&mut framebuffer, &mut uniforms, &planet_mesh, atmosphere, &atmosphere_render_state);
             },
             GAS_GIANT => {
-                framebuffer.set_current_color(0x00FFAA);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "gas_giant_shader");
+                render(&mut framebuffer, &uniforms, &planet_mesh, "gas_giant_shader", &planet_render_state);
+
+                // Envoltura de hidrógeno más alta y con más bruma que la de un planeta rocoso
+                let atmosphere = Atmosphere::new(translation, planet_radius, star.position - translation)
+                    .with_rayleigh(Vec3::new(0.06, 0.12, 0.25), 0.04 * planet_radius)
+                    .with_mie(0.1, 0.015 * planet_radius, 0.7)
+                    .with_sun_radiance(star.emission * 10.0);
+                render_atmosphere(&mut framebuffer, &mut uniforms, &planet_mesh, atmosphere, &atmosphere_render_state);
             },
             GAS_GIANT_WITH_RINGS => {
-                framebuffer.set_current_color(0x00FFAA);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "gas_giant_with_rings");
+                let ring_scale = scale * 1.2;
+                let ring_model_matrix = create_model_matrix(translation, ring_scale, rotation);
+
+                // El planeta proyecta su sombra sobre los anillos y los anillos sobre el planeta
+                let mut shadow_map = ShadowMap::new(SHADOW_MAP_SIZE, star.position, translation, ring_mesh.bounding_radius() * ring_scale);
+                shadow_map.render(&planet_mesh, &model_matrix);
+                shadow_map.render(&ring_mesh, &ring_model_matrix);
+                uniforms.shadow_map = Some(shadow_map);
+
+                render(&mut framebuffer, &uniforms, &planet_mesh, "gas_giant_with_rings", &planet_render_state);
 
-                let ring_model_matrix = create_model_matrix(translation, scale * 1.2, rotation);
                 uniforms.model_matrix = ring_model_matrix;
-                render(&mut framebuffer, &uniforms, &ring_vertex_array, "ring");
+                render(&mut framebuffer, &uniforms, &ring_mesh, "ring", &ring_render_state);
             },
             PLANET_COLORFUL => {
-                framebuffer.set_current_color(0x00FFAA);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "colorful");
+                render(&mut framebuffer, &uniforms, &planet_mesh, "colorful", &planet_render_state);
             },
             PLANET_EXOTIC => {
-                framebuffer.set_current_color(0x00FFAA);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "exotic");
+                render(&mut framebuffer, &uniforms, &planet_mesh, "exotic", &planet_render_state);
             },
             DARK_RED => {
-                framebuffer.set_current_color(0x00FFAA);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "dark_red");
+                render(&mut framebuffer, &uniforms, &planet_mesh, "dark_red", &planet_render_state);
             },
             ROCKY_PLANET_WITH_MOON => {
                 let planet_translation = translation;
                 let planet_model_matrix = create_model_matrix(planet_translation, scale, rotation);
-                uniforms.model_matrix = planet_model_matrix;
 
-                framebuffer.set_current_color(0xAAAAAA);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "rocky_planet_with_moon_shader");
-
-                let orbit_radius = 150.0;
                 let orbit_speed = 0.02;
                 let angle = time as f32 * orbit_speed;
 
-                moon.position.x = planet_translation.x + orbit_radius * angle.cos();
-                moon.position.y = planet_translation.y + orbit_radius * angle.sin();
+                moon.position.x = planet_translation.x + moon_orbit_radius * angle.cos();
+                moon.position.y = planet_translation.y + moon_orbit_radius * angle.sin();
+                let moon_model_matrix = create_model_matrix(moon.position, moon.scale, moon.rotation);
+
+                // La luna eclipsa al planeta cuando pasa frente a la estrella, y el planeta a la luna
+                let shadow_radius = moon_orbit_radius + moon.scale * planet_mesh.bounding_radius();
+                let mut shadow_map = ShadowMap::new(SHADOW_MAP_SIZE, star.position, planet_translation, shadow_radius);
+                shadow_map.render(&planet_mesh, &planet_model_matrix);
+                shadow_map.render(&planet_mesh, &moon_model_matrix);
+                uniforms.shadow_map = Some(shadow_map);
+
+                uniforms.model_matrix = planet_model_matrix;
+                render(&mut framebuffer, &uniforms, &planet_mesh, "rocky_planet_with_moon_shader", &planet_render_state);
 
                 // Renderizar la luna
-                let moon_model_matrix = create_model_matrix(moon.position, moon.scale, moon.rotation);
                 uniforms.model_matrix = moon_model_matrix;
-                framebuffer.set_current_color(0x888888);
-                render(&mut framebuffer, &uniforms, &planet_vertex_array, "moon_shader");
+                render(&mut framebuffer, &uniforms, &planet_mesh, "moon_shader", &planet_render_state);
+            },
+            METAL_SPHERE => {
+                // Esfera con el material del casco de las naves. Un foco la ilumina desde arriba
+                // y una luz azulada y tenue llega por el lado contrario a la estrella
+                let floodlight_position = translation + Vec3::new(1.5, 2.5, 2.0);
+                uniforms.lights.push(
+                    Light::spot(floodlight_position, translation - floodlight_position, 0.2, 0.35, Color::from_f32(1.0, 0.9, 0.75), 4.0)
+                        .with_attenuation(1.0, 0.1, 0.05),
+                );
+                uniforms.lights.push(Light::directional(star.position - translation, Color::from_f32(0.3, 0.45, 0.7), 0.15));
+
+                render(&mut framebuffer, &uniforms, &planet_mesh, "spacecraft", &planet_render_state);
             },
             _ => {},
         }
 
+        // Con sombreado diferido, el color se calcula aquí una vez por píxel
+        shade_deferred(&mut framebuffer, &uniforms);
+
+        // Las superficies translúcidas se mezclan sobre la escena opaca ya sombreada
+        render_transparent(&mut framebuffer, &uniforms);
+
+        // Las líneas con antialiasing se mezclan con la escena ya sombreada
+        if selected_object == ROCKY_PLANET_WITH_MOON {
+            uniforms.model_matrix = Mat4::identity();
+            render_lines(&mut framebuffer, &uniforms, &moon_orbit_path, &orbit_style);
+        }
+
+        framebuffer.resolve_samples();
+
+        if post_processing {
+            let post_process = match selected_object {
+                STAR => &star_post_process,
+                ROCKY_PLANET_WITH_MOON => &moon_post_process,
+                _ => &planet_post_process,
+            };
+            post_process.apply(&mut framebuffer, time);
+        }
+
+        framebuffer.tone_map();
+
         window
             .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
             .unwrap();
@@ -296,57 +570,48 @@ fn main() {
     }
 }
 
-fn render_rings(framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
-    let ring_inner_radius = 1.2;
-    let ring_outer_radius = 1.8;
-
-    for x in -400..400 {
-        for y in -400..400 {
-            let xf = x as f32 / 100.0;
-            let yf = y as f32 / 100.0;
-            let distance = (xf.powi(2) + yf.powi(2)).sqrt();
-
-            if distance > ring_inner_radius && distance < ring_outer_radius {
-                let fragment = Fragment::new(
-                    Vec2::new(xf, yf),
-                    Color::new(0, 0, 0),
-                    1.0,
-                    Vec3::new(0.0, 0.0, 1.0),
-                    1.0,
-                    Vec3::new(xf, yf, 0.0),
-                );
+// El título de la ventana indica qué ruta de sombreado está activa
+fn shading_title(framebuffer: &Framebuffer) -> &'static str {
+    match (framebuffer.is_deferred(), framebuffer.samples > 1) {
+        (true, _) => "Planetary System - sombreado diferido",
+        (false, true) => "Planetary System - sombreado directo (MSAA)",
+        (false, false) => "Planetary System - sombreado directo",
+    }
+}
 
-                let ring_color = ring_shader(&fragment, uniforms);
-                
-                let x_screen = (xf * 100.0 + framebuffer.width as f32 / 2.0) as usize;
-                let y_screen = (yf * 100.0 + framebuffer.height as f32 / 2.0) as usize;
-                
-                if x_screen < framebuffer.width && y_screen < framebuffer.height {
-                    framebuffer.set_current_color(ring_color.to_hex());
-                    framebuffer.point(x_screen, y_screen, 1.0);
-                }
-            }
+// Muestras de MSAA elegidas al iniciar, por ejemplo `cargo run --release -- --msaa 4`
+fn parse_msaa_samples() -> usize {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(position) = args.iter().position(|arg| arg == "--msaa") else {
+        return 1;
+    };
+
+    match args.get(position + 1).and_then(|value| value.parse().ok()) {
+        Some(samples @ (1 | 2 | 4 | 8)) => samples,
+        _ => {
+            eprintln!("--msaa acepta 1, 2, 4 u 8; se usará 1");
+            1
         }
     }
 }
 
-fn handle_input(window: &Window, translation: &mut Vec3, rotation: &mut Vec3, scale: &mut f32) {
-    let move_speed = 10.0; 
+fn handle_input(window: &Window, camera: &mut Camera, rotation: &mut Vec3) {
+    let orbit_speed = 0.05;
     let rotation_speed = 0.2; 
-    let zoom_speed = 2.0; 
+    let zoom_speed = 0.2; 
 
     // Movimiento de cámara
     if window.is_key_down(Key::Left) {
-        translation.x -= move_speed; 
+        camera.orbit(orbit_speed, 0.0);
     }
     if window.is_key_down(Key::Right) {
-        translation.x += move_speed; 
+        camera.orbit(-orbit_speed, 0.0);
     }
     if window.is_key_down(Key::Up) {
-        translation.y -= move_speed; 
+        camera.orbit(0.0, orbit_speed);
     }
     if window.is_key_down(Key::Down) {
-        translation.y += move_speed; 
+        camera.orbit(0.0, -orbit_speed);
     }
 
     // Control de rotación 
@@ -365,9 +630,9 @@ fn handle_input(window: &Window, translation: &mut Vec3, rotation: &mut Vec3, sc
 
     // Zoom
     if window.is_key_down(Key::Q) {
-        *scale += zoom_speed;  
+        camera.zoom(zoom_speed);
     }
     if window.is_key_down(Key::E) {
-        *scale -= zoom_speed;  
+        camera.zoom(-zoom_speed);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,121 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::light::Light;
+
+// Parámetros del modelo de Blinn-Phong para una superficie
+#[derive(Debug, Clone, Copy)]
+pub struct Material {
+    pub diffuse: Color,
+    pub specular: Color,
+    // Exponente del brillo: valores altos dan reflejos pequeños e intensos (hielo, océano)
+    pub shininess: f32,
+    pub emissive: Color,
+    // Color que la superficie muestra aunque ninguna fuente la ilumine, como el término
+    // ambiental que ya sumaban los shaders de los planetas
+    pub ambient: Color,
+}
+
+impl Material {
+    // Superficie mate del color dado, sin brillo, emisión ni luz ambiental
+    pub fn new(diffuse: Color) -> Self {
+        Material {
+            diffuse,
+            specular: Color::black(),
+            shininess: 32.0,
+            emissive: Color::black(),
+            ambient: Color::black(),
+        }
+    }
+
+    pub fn with_specular(self, specular: Color, shininess: f32) -> Self {
+        Material { specular, shininess, ..self }
+    }
+
+    pub fn with_ambient(self, ambient: Color) -> Self {
+        Material { ambient, ..self }
+    }
+
+    // `normal` y `view_dir` (hacia la cámara) normalizados y en espacio de mundo.
+    // `visibility` da la fracción de cada luz que no está tapada (1.0 sin sombras)
+    pub fn shade(
+        &self,
+        normal: &Vec3,
+        view_dir: &Vec3,
+        world_position: &Vec3,
+        lights: &[Light],
+        visibility: impl Fn(&Light) -> f32,
+    ) -> Color {
+        let direct = lights.iter().fold(Color::black(), |total, light| {
+            let (light_dir, radiance) = light.incident(world_position);
+            let n_dot_l = normal.dot(&light_dir);
+            if n_dot_l <= 0.0 {
+                return total;
+            }
+            let visible = visibility(light);
+            if visible <= 0.0 {
+                return total;
+            }
+
+            let diffuse = self.diffuse * n_dot_l;
+            let half_dir = (light_dir + view_dir).try_normalize(f32::EPSILON).unwrap_or(*normal);
+            let specular = self.specular * normal.dot(&half_dir).max(0.0).powf(self.shininess);
+
+            total + (diffuse + specular).blend_multiply(&radiance) * visible
+        });
+
+        direct + self.ambient + self.emissive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Luz blanca que llega a 45° desde +x sobre una superficie que mira a +z
+    fn light() -> Light {
+        Light::directional(Vec3::new(-1.0, 0.0, -1.0), Color::from_f32(1.0, 1.0, 1.0), 1.0)
+    }
+
+    // Solo brillo especular, visto desde `view_angle` radianes respecto a la normal en el plano xz
+    fn specular(shininess: f32, view_angle: f32) -> f32 {
+        let material = Material::new(Color::black()).with_specular(Color::from_f32(1.0, 1.0, 1.0), shininess);
+        let view_dir = Vec3::new(view_angle.sin(), 0.0, view_angle.cos());
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        material.shade(&normal, &view_dir, &Vec3::zeros(), &[light()], |_| 1.0).r()
+    }
+
+    #[test]
+    fn specular_peaks_where_the_half_vector_meets_the_normal() {
+        // La vista reflejada, a -45°, deja el vector medio sobre la normal
+        let mirror = -std::f32::consts::FRAC_PI_4;
+        assert!((specular(32.0, mirror) - 1.0).abs() < 1e-4, "{}", specular(32.0, mirror));
+        for degrees in (-80..=80).filter(|&degrees| degrees != -45) {
+            let view_angle = (degrees as f32).to_radians();
+            assert!(specular(32.0, view_angle) < specular(32.0, mirror), "{degrees}°");
+        }
+    }
+
+    #[test]
+    fn higher_shininess_narrows_the_highlight() {
+        let mirror = -std::f32::consts::FRAC_PI_4;
+        let off_peak = mirror + 20f32.to_radians();
+        assert!(specular(64.0, off_peak) < specular(8.0, off_peak) * 0.5);
+        assert!((specular(64.0, mirror) - specular(8.0, mirror)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn light_behind_the_surface_adds_nothing() {
+        let material = Material::new(Color::from_f32(0.8, 0.6, 0.4)).with_specular(Color::from_f32(1.0, 1.0, 1.0), 16.0);
+        let normal = Vec3::new(0.0, 0.0, -1.0);
+        let view_dir = Vec3::new(0.0, 0.0, -1.0);
+        let lit = |material: &Material| material.shade(&normal, &view_dir, &Vec3::zeros(), &[light()], |_| 1.0);
+
+        let black = lit(&material);
+        assert_eq!([black.r(), black.g(), black.b()], [0.0; 3]);
+
+        // Las luces no llegan, pero la emisión se suma igual
+        let glowing = Material { emissive: Color::from_f32(0.2, 0.1, 0.0), ..material };
+        let glow = lit(&glowing);
+        assert_eq!([glow.r(), glow.g(), glow.b()], [0.2, 0.1, 0.0]);
+    }
+}
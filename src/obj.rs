@@ -1,4 +1,3 @@
-use tobj;
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
 
@@ -6,6 +5,22 @@ pub struct Obj {
     meshes: Vec<Mesh>,
 }
 
+// Malla indexada: cada vértice único aparece una vez y los triángulos se arman
+// con tripletas de índices
+pub struct IndexedMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl IndexedMesh {
+    // Distancia del origen del modelo al vértice más lejano
+    pub fn bounding_radius(&self) -> f32 {
+        self.vertices.iter()
+            .map(|vertex| vertex.position.magnitude())
+            .fold(0.0, f32::max)
+    }
+}
+
 struct Mesh {
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
@@ -25,10 +40,10 @@ impl Obj {
             let mesh = model.mesh;
             Mesh {
                 vertices: mesh.positions.chunks(3)
-                    .map(|v| Vec3::new(v[0], -v[1], v[2]))
+                    .map(|v| Vec3::new(v[0], v[1], v[2]))
                     .collect(),
                 normals: mesh.normals.chunks(3)
-                    .map(|n| Vec3::new(n[0], -n[1], n[2]))
+                    .map(|n| Vec3::new(n[0], n[1], n[2]))
                     .collect(),
                 texcoords: mesh.texcoords.chunks(2)
                     .map(|t| Vec2::new(t[0], 1.0 - t[1]))
@@ -40,23 +55,27 @@ impl Obj {
         Ok(Obj { meshes })
     }
 
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+    pub fn get_indexed_mesh(&self) -> IndexedMesh {
         let mut vertices = Vec::new();
+        let mut indices = Vec::new();
 
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh.normals.get(index as usize)
+            let offset = vertices.len() as u32;
+
+            for (index, &position) in mesh.vertices.iter().enumerate() {
+                let normal = mesh.normals.get(index)
                     .cloned()
                     .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh.texcoords.get(index as usize)
+                let tex_coords = mesh.texcoords.get(index)
                     .cloned()
                     .unwrap_or(Vec2::new(0.0, 0.0));
 
                 vertices.push(Vertex::new(position, normal, tex_coords));
             }
+
+            indices.extend(mesh.indices.iter().map(|&index| index + offset));
         }
 
-        vertices
+        IndexedMesh { vertices, indices }
     }
-}
\ No newline at end of file
+}
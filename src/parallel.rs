@@ -0,0 +1,26 @@
+use std::thread;
+use crate::rasterizer::worker_count;
+
+// Reemplaza cada píxel de `image` por `shade(x, y, pixel)`, repartiendo las filas entre hilos.
+// Sirve para cualquier imagen guardada por filas, no solo de colores
+pub(crate) fn map_pixels<T, F>(image: &mut [T], width: usize, shade: F)
+where
+    T: Copy + Send,
+    F: Fn(usize, usize, T) -> T + Sync,
+{
+    let height = image.len() / width;
+    let rows_per_chunk = height.div_ceil(worker_count()).max(1);
+    let shade = &shade;
+
+    thread::scope(|scope| {
+        for (chunk_index, chunk) in image.chunks_mut(rows_per_chunk * width).enumerate() {
+            scope.spawn(move || {
+                let first = chunk_index * rows_per_chunk * width;
+                for (offset, pixel) in chunk.iter_mut().enumerate() {
+                    let index = first + offset;
+                    *pixel = shade(index % width, index / width, *pixel);
+                }
+            });
+        }
+    });
+}
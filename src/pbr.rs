@@ -0,0 +1,185 @@
+use std::f32::consts::PI;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::light::Light;
+
+// Reflectancia a incidencia normal de los dieléctricos (plástico, pintura, roca)
+const DIELECTRIC_F0: f32 = 0.04;
+
+// Material físico metálico/rugosidad con BRDF de Cook-Torrance (distribución GGX,
+// sombreado de Smith y Fresnel de Schlick)
+#[derive(Debug, Clone, Copy)]
+pub struct PbrMaterial {
+    pub albedo: Color,
+    // 0.0 dieléctrico, 1.0 metal; los metales tiñen el brillo con el albedo y no tienen difuso
+    pub metallic: f32,
+    // 0.0 espejo, 1.0 completamente mate
+    pub roughness: f32,
+    pub emission: Color,
+    // Fracción de la luz ambiental que llega a la superficie (1.0 sin oclusión)
+    pub ambient_occlusion: f32,
+}
+
+impl PbrMaterial {
+    pub fn new(albedo: Color, metallic: f32, roughness: f32) -> Self {
+        PbrMaterial {
+            albedo,
+            metallic: metallic.clamp(0.0, 1.0),
+            // Con rugosidad 0 la GGX es una delta; se limita para que el brillo sea visible
+            roughness: roughness.clamp(0.045, 1.0),
+            emission: Color::black(),
+            ambient_occlusion: 1.0,
+        }
+    }
+
+    pub fn with_emission(self, emission: Color) -> Self {
+        PbrMaterial { emission, ..self }
+    }
+
+    pub fn with_ambient_occlusion(self, ambient_occlusion: f32) -> Self {
+        PbrMaterial { ambient_occlusion: ambient_occlusion.clamp(0.0, 1.0), ..self }
+    }
+
+    // Reflectancia a incidencia normal
+    fn f0(&self) -> Color {
+        let dielectric = Color::from_f32(DIELECTRIC_F0, DIELECTRIC_F0, DIELECTRIC_F0);
+        dielectric.lerp(&self.albedo, self.metallic)
+    }
+
+    // Valor de la BRDF para la luz que llega por `light_dir` y sale hacia `view_dir`
+    // (sin multiplicar por el coseno). Todos los vectores normalizados
+    pub fn brdf(&self, normal: &Vec3, view_dir: &Vec3, light_dir: &Vec3) -> Color {
+        let n_dot_l = normal.dot(light_dir);
+        let n_dot_v = normal.dot(view_dir);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Color::black();
+        }
+
+        let half_dir = (light_dir + view_dir).normalize();
+        let n_dot_h = normal.dot(&half_dir).max(0.0);
+        let v_dot_h = view_dir.dot(&half_dir).max(0.0);
+
+        let alpha = self.roughness * self.roughness;
+        let distribution = ggx_distribution(n_dot_h, alpha);
+        let visibility = smith_ggx(n_dot_l, alpha) * smith_ggx(n_dot_v, alpha);
+        let fresnel = fresnel_schlick(self.f0(), v_dot_h);
+
+        let specular = fresnel * (distribution * visibility / (4.0 * n_dot_l * n_dot_v));
+
+        // La luz que no se refleja al entrar ni al salir se difunde; los metales la absorben.
+        // Con el Fresnel de las dos direcciones el difuso no supera la energía que queda
+        let reflected_in = fresnel_schlick(self.f0(), n_dot_l);
+        let reflected_out = fresnel_schlick(self.f0(), n_dot_v);
+        let diffuse_weight = Color::from_f32(
+            (1.0 - reflected_in.r()) * (1.0 - reflected_out.r()),
+            (1.0 - reflected_in.g()) * (1.0 - reflected_out.g()),
+            (1.0 - reflected_in.b()) * (1.0 - reflected_out.b()),
+        ) * ((1.0 - self.metallic) / PI);
+        let diffuse = self.albedo.blend_multiply(&diffuse_weight);
+
+        diffuse + specular
+    }
+
+    // `visibility` da la fracción de cada luz que no está tapada. `ambient` es la luz
+    // ambiental de la escena, atenuada por la oclusión del material
+    pub fn shade(
+        &self,
+        normal: &Vec3,
+        view_dir: &Vec3,
+        world_position: &Vec3,
+        lights: &[Light],
+        ambient: Color,
+        visibility: impl Fn(&Light) -> f32,
+    ) -> Color {
+        let direct = lights.iter().fold(Color::black(), |total, light| {
+            let (light_dir, radiance) = light.incident(world_position);
+            let n_dot_l = normal.dot(&light_dir);
+            if n_dot_l <= 0.0 {
+                return total;
+            }
+            let reflected = self.brdf(normal, view_dir, &light_dir).blend_multiply(&radiance);
+            total + reflected * (n_dot_l * visibility(light))
+        });
+
+        let ambient = ambient.blend_multiply(&self.albedo) * self.ambient_occlusion;
+        direct + ambient + self.emission
+    }
+}
+
+// Distribución de normales de Trowbridge-Reitz (GGX)
+fn ggx_distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * denominator * denominator)
+}
+
+// Término de Smith para una dirección, con la aproximación de Schlick (k = alpha / 2)
+fn smith_ggx(n_dot_x: f32, alpha: f32) -> f32 {
+    let k = alpha / 2.0;
+    n_dot_x / (n_dot_x * (1.0 - k) + k)
+}
+
+fn fresnel_schlick(f0: Color, cos_theta: f32) -> Color {
+    let weight = (1.0 - cos_theta).clamp(0.0, 1.0).powi(5);
+    f0.map(|f| f + (1.0 - f) * weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Integra BRDF * coseno sobre el hemisferio para una dirección de vista: es la
+    // fracción de energía que la superficie devuelve bajo una luz blanca uniforme
+    fn directional_albedo(material: &PbrMaterial, view_angle: f32) -> Color {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let view_dir = Vec3::new(view_angle.sin(), 0.0, view_angle.cos());
+
+        // dω = d(cos θ) dφ; punto medio en cada celda
+        let (cos_steps, phi_steps) = (1024, 256);
+        let cell = (1.0 / cos_steps as f32) * (2.0 * PI / phi_steps as f32);
+        let mut total = Color::black();
+        for i in 0..cos_steps {
+            let cos_theta = (i as f32 + 0.5) / cos_steps as f32;
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            for j in 0..phi_steps {
+                let phi = (j as f32 + 0.5) / phi_steps as f32 * 2.0 * PI;
+                let light_dir = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+                total = total + material.brdf(&normal, &view_dir, &light_dir) * (cos_theta * cell);
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn white_furnace_never_creates_energy() {
+        let white = Color::from_f32(1.0, 1.0, 1.0);
+        for metallic in [0.0, 0.5, 1.0] {
+            for roughness in [0.2, 0.5, 0.8, 1.0] {
+                let material = PbrMaterial::new(white, metallic, roughness);
+                for view_angle in [0.0, 0.6, 1.2, 1.5] {
+                    let albedo = directional_albedo(&material, view_angle);
+                    assert!(
+                        albedo.r() <= 1.01,
+                        "metallic {} roughness {} view {}: {}",
+                        metallic, roughness, view_angle, albedo.r()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn white_furnace_smooth_metal_reflects_almost_everything() {
+        // Sin múltiples rebotes la GGX pierde algo de energía, poca si la superficie es lisa
+        let material = PbrMaterial::new(Color::from_f32(1.0, 1.0, 1.0), 1.0, 0.2);
+        let albedo = directional_albedo(&material, 0.0);
+        assert!(albedo.r() > 0.95, "{}", albedo.r());
+    }
+
+    #[test]
+    fn white_furnace_white_dielectric_is_nearly_lossless() {
+        let material = PbrMaterial::new(Color::from_f32(1.0, 1.0, 1.0), 0.0, 0.5);
+        let albedo = directional_albedo(&material, 0.0);
+        assert!(albedo.r() > 0.9 && albedo.r() <= 1.01, "{}", albedo.r());
+    }
+}
@@ -0,0 +1,219 @@
+use nalgebra_glm::Mat4;
+use crate::vertex::Vertex;
+use crate::triangle::edge_function;
+use crate::color::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Back,
+    // Ninguna escena descarta todavía las caras frontales
+    #[allow(dead_code)]
+    Front,
+}
+
+// Orden de los vértices (visto desde la cámara) que define la cara frontal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+impl FrontFace {
+    pub fn flipped(self) -> Self {
+        match self {
+            FrontFace::CounterClockwise => FrontFace::Clockwise,
+            FrontFace::Clockwise => FrontFace::CounterClockwise,
+        }
+    }
+}
+
+// Modos de relleno para depurar la teselación de las mallas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillMode {
+    Solid,
+    Wireframe,
+    SolidWireframe,
+    Points,
+}
+
+impl FillMode {
+    pub fn next(self) -> Self {
+        match self {
+            FillMode::Solid => FillMode::Wireframe,
+            FillMode::Wireframe => FillMode::SolidWireframe,
+            FillMode::SolidWireframe => FillMode::Points,
+            FillMode::Points => FillMode::Solid,
+        }
+    }
+}
+
+// Cómo se combina el color del shader (origen) con el del framebuffer (destino)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,
+    // Ninguna superficie de las escenas suma todavía su luz sin taparse
+    #[allow(dead_code)]
+    Additive,
+    // El shader ya multiplicó su color por alfa
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub fn is_transparent(self) -> bool {
+        self != BlendMode::Opaque
+    }
+
+    pub fn blend(self, source: Color, destination: Color) -> Color {
+        let alpha = source.a();
+        let (source_factor, destination_factor) = match self {
+            BlendMode::Opaque => (1.0, 0.0),
+            BlendMode::Alpha => (alpha, 1.0 - alpha),
+            BlendMode::Additive => (alpha, 1.0),
+            BlendMode::Premultiplied => (1.0, 1.0 - alpha),
+        };
+
+        (source * source_factor + destination * destination_factor).with_alpha(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderState {
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub fill_mode: FillMode,
+    pub blend_mode: BlendMode,
+}
+
+impl RenderState {
+    pub fn new(cull_mode: CullMode, front_face: FrontFace) -> Self {
+        RenderState { cull_mode, front_face, fill_mode: FillMode::Solid, blend_mode: BlendMode::Opaque }
+    }
+
+    // Para mallas que deben verse por ambos lados, como los anillos
+    pub fn double_sided() -> Self {
+        RenderState::new(CullMode::None, FrontFace::CounterClockwise)
+    }
+
+    // Las superficies translúcidas dejan ver su cara trasera, así que no se descartan
+    pub fn transparent(blend_mode: BlendMode) -> Self {
+        RenderState { blend_mode, ..RenderState::double_sided() }
+    }
+
+    // Una matriz de modelo que refleja la malla (determinante negativo) invierte el orden
+    // de sus vértices en pantalla; así las caras visibles siguen siendo las mismas
+    pub fn for_model(&self, model_matrix: &Mat4) -> Self {
+        if model_matrix.determinant() < 0.0 {
+            RenderState { front_face: self.front_face.flipped(), ..*self }
+        } else {
+            *self
+        }
+    }
+
+    // Recibe el triángulo ya en coordenadas de pantalla
+    pub fn is_culled(&self, v1: &Vertex, v2: &Vertex, v3: &Vertex) -> bool {
+        let area = edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position);
+
+        // Los triángulos degenerados no cubren ningún píxel
+        if area == 0.0 {
+            return true;
+        }
+
+        // El viewport invierte el eje y, así que un triángulo antihorario
+        // en NDC tiene área positiva en pantalla
+        let is_front = match self.front_face {
+            FrontFace::CounterClockwise => area > 0.0,
+            FrontFace::Clockwise => area < 0.0,
+        };
+
+        match self.cull_mode {
+            CullMode::None => false,
+            CullMode::Back => !is_front,
+            CullMode::Front => is_front,
+        }
+    }
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        RenderState::new(CullMode::Back, FrontFace::CounterClockwise)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec3};
+    use crate::obj::Obj;
+
+    fn screen_triangle(points: [(f32, f32); 3]) -> [Vertex; 3] {
+        points.map(|(x, y)| Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0)))
+    }
+
+    #[test]
+    fn cull_modes_against_both_windings() {
+        // Área positiva en pantalla: antihorario en NDC, ya que el viewport invierte y
+        let counter_clockwise = screen_triangle([(0.0, 0.0), (0.0, 10.0), (10.0, 0.0)]);
+        let clockwise = screen_triangle([(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]);
+
+        // (estado, ¿se descarta el antihorario?, ¿se descarta el horario?)
+        let cases = [
+            (RenderState::new(CullMode::Back, FrontFace::CounterClockwise), false, true),
+            (RenderState::new(CullMode::Front, FrontFace::CounterClockwise), true, false),
+            (RenderState::new(CullMode::None, FrontFace::CounterClockwise), false, false),
+            (RenderState::new(CullMode::Back, FrontFace::Clockwise), true, false),
+            (RenderState::new(CullMode::Front, FrontFace::Clockwise), false, true),
+            (RenderState::new(CullMode::None, FrontFace::Clockwise), false, false),
+            (RenderState::double_sided(), false, false),
+            (RenderState::transparent(BlendMode::Alpha), false, false),
+        ];
+
+        for (state, culls_counter_clockwise, culls_clockwise) in cases {
+            let [a, b, c] = &counter_clockwise;
+            assert_eq!(state.is_culled(a, b, c), culls_counter_clockwise, "{:?}", state);
+            let [a, b, c] = &clockwise;
+            assert_eq!(state.is_culled(a, b, c), culls_clockwise, "{:?}", state);
+        }
+
+        // Los triángulos degenerados se descartan siempre
+        let [a, b, c] = &screen_triangle([(0.0, 0.0), (5.0, 5.0), (10.0, 10.0)]);
+        assert!(RenderState::double_sided().is_culled(a, b, c));
+    }
+
+    #[test]
+    fn mirrored_model_keeps_the_same_visible_faces() {
+        let sphere = Obj::load("assets/sphere.obj").expect("Failed to load obj").get_indexed_mesh();
+        let mirror = Mat4::new_nonuniform_scaling(&Vec3::new(-1.0, 1.0, 1.0));
+
+        for model_matrix in [Mat4::identity(), mirror] {
+            let state = RenderState::default().for_model(&model_matrix);
+            let mut visible = 0;
+
+            for indices in sphere.indices.chunks_exact(3) {
+                // Proyección ortográfica con la cámara en +z y el eje y invertido del viewport
+                let triangle = [0, 1, 2].map(|corner| {
+                    let vertex = &sphere.vertices[indices[corner] as usize];
+                    let world = model_matrix.transform_point(&vertex.position.into());
+                    let mut screen = vertex.clone();
+                    screen.transformed_position = Vec3::new(world.x * 50.0 + 100.0, -world.y * 50.0 + 100.0, world.z);
+                    screen
+                });
+                let normal = triangle.iter().fold(Vec3::zeros(), |sum, vertex| sum + vertex.normal);
+                let facing = model_matrix.transform_vector(&normal).normalize().z;
+
+                // Las caras casi de canto pueden caer a cualquier lado
+                if facing.abs() < 0.2 {
+                    continue;
+                }
+                let culled = state.is_culled(&triangle[0], &triangle[1], &triangle[2]);
+                assert_eq!(culled, facing < 0.0, "facing {}", facing);
+                if !culled {
+                    visible += 1;
+                }
+            }
+
+            assert!(visible > 0);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::parallel::map_pixels;
+
+// Efectos que se aplican al color HDR lineal del framebuffer, antes del tone mapping
+#[derive(Debug, Clone, Copy)]
+pub enum PostEffect {
+    // La luz por encima de `threshold` se difumina alrededor de su origen. `radius` es
+    // la desviación del desenfoque gaussiano en píxeles
+    Bloom { threshold: f32, intensity: f32, radius: f32 },
+    // Oscurece las esquinas a partir de `radius` (0 en el centro, 1 en la esquina)
+    Vignette { strength: f32, radius: f32 },
+    FilmGrain { amount: f32 },
+    // Separa los canales rojo y azul hacia los bordes, hasta `offset` píxeles
+    ChromaticAberration { offset: f32 },
+}
+
+// Cadena de efectos que se aplican en orden; cada escena puede tener la suya
+#[derive(Debug, Clone, Default)]
+pub struct PostProcess {
+    pub effects: Vec<PostEffect>,
+}
+
+impl PostProcess {
+    pub fn new(effects: Vec<PostEffect>) -> Self {
+        PostProcess { effects }
+    }
+
+    // Se llama después de Framebuffer::resolve_samples y antes de tone_map
+    pub fn apply(&self, framebuffer: &mut Framebuffer, time: u32) {
+        let width = framebuffer.width;
+        let image = &mut framebuffer.hdr_buffer;
+
+        for effect in &self.effects {
+            match *effect {
+                PostEffect::Bloom { threshold, intensity, radius } => bloom(image, width, threshold, intensity, radius),
+                PostEffect::Vignette { strength, radius } => vignette(image, width, strength, radius),
+                PostEffect::FilmGrain { amount } => film_grain(image, width, amount, time),
+                PostEffect::ChromaticAberration { offset } => chromatic_aberration(image, width, offset),
+            }
+        }
+    }
+}
+
+fn bloom(image: &mut [Color], width: usize, threshold: f32, intensity: f32, radius: f32) {
+    let height = image.len() / width;
+
+    // Bright-pass a media resolución: solo queda la luz que supera el umbral
+    let (half_width, half_height) = (width.div_ceil(2), height.div_ceil(2));
+    let mut bright = vec![Color::black(); half_width * half_height];
+    let source: &[Color] = image;
+    map_pixels(&mut bright, half_width, |x, y, _| {
+        let mut sum = Color::black();
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let sx = (x * 2 + dx).min(width - 1);
+            let sy = (y * 2 + dy).min(height - 1);
+            sum = sum + source[sy * width + sx];
+        }
+        let color = sum * 0.25;
+        let luminance = color.luminance();
+        if luminance <= threshold {
+            Color::black()
+        } else {
+            color * ((luminance - threshold) / luminance)
+        }
+    });
+
+    // Desenfoque gaussiano separable: horizontal y luego vertical
+    let kernel = gaussian_kernel(radius / 2.0);
+    let reach = (kernel.len() / 2) as isize;
+    let mut blurred = vec![Color::black(); bright.len()];
+    map_pixels(&mut blurred, half_width, |x, y, _| {
+        kernel.iter().enumerate().fold(Color::black(), |sum, (k, &weight)| {
+            let sx = (x as isize + k as isize - reach).clamp(0, half_width as isize - 1) as usize;
+            sum + bright[y * half_width + sx] * weight
+        })
+    });
+    map_pixels(&mut bright, half_width, |x, y, _| {
+        kernel.iter().enumerate().fold(Color::black(), |sum, (k, &weight)| {
+            let sy = (y as isize + k as isize - reach).clamp(0, half_height as isize - 1) as usize;
+            sum + blurred[sy * half_width + x] * weight
+        })
+    });
+
+    // Composición: se suma el brillo difuminado, interpolado a resolución completa
+    map_pixels(image, width, |x, y, color| {
+        let glow = sample_bilinear(&bright, half_width, half_height, (x as f32 + 0.5) / 2.0 - 0.5, (y as f32 + 0.5) / 2.0 - 0.5);
+        color + glow * intensity
+    });
+}
+
+// Pesos normalizados de una gaussiana de desviación `sigma`, cubriendo 3 sigmas
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let sigma = sigma.max(0.5);
+    let reach = (sigma * 3.0).ceil() as i32;
+    let weights: Vec<f32> = (-reach..=reach)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+fn sample_bilinear(image: &[Color], width: usize, height: usize, x: f32, y: f32) -> Color {
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+    let top = image[y0 * width + x0].lerp(&image[y0 * width + x1], tx);
+    let bottom = image[y1 * width + x0].lerp(&image[y1 * width + x1], tx);
+    top.lerp(&bottom, ty)
+}
+
+// Desplazamiento (dx, dy) del píxel respecto al centro y su longitud, 1.0 en las esquinas
+fn center_offset(x: usize, y: usize, width: usize, height: usize) -> (f32, f32, f32) {
+    let (half_width, half_height) = (width as f32 / 2.0, height as f32 / 2.0);
+    let half_diagonal = (half_width * half_width + half_height * half_height).sqrt();
+    let dx = (x as f32 + 0.5 - half_width) / half_diagonal;
+    let dy = (y as f32 + 0.5 - half_height) / half_diagonal;
+    (dx, dy, (dx * dx + dy * dy).sqrt())
+}
+
+fn vignette(image: &mut [Color], width: usize, strength: f32, radius: f32) {
+    let height = image.len() / width;
+    map_pixels(image, width, |x, y, color| {
+        let (_, _, distance) = center_offset(x, y, width, height);
+        let falloff = ((distance - radius) / (1.0 - radius).max(1e-3)).clamp(0.0, 1.0);
+        color * (1.0 - strength * falloff * falloff)
+    });
+}
+
+fn film_grain(image: &mut [Color], width: usize, amount: f32, time: u32) {
+    map_pixels(image, width, |x, y, color| {
+        color * (1.0 + amount * grain_noise(x as u32, y as u32, time))
+    });
+}
+
+// Ruido blanco en [-1, 1] que cambia en cada frame
+fn grain_noise(x: u32, y: u32, time: u32) -> f32 {
+    let mut hash = x.wrapping_mul(0x8DA6_B343) ^ y.wrapping_mul(0xD816_3841) ^ time.wrapping_mul(0xCB1A_B31F);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846C_A68B);
+    hash ^= hash >> 16;
+    hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+fn chromatic_aberration(image: &mut [Color], width: usize, offset: f32) {
+    let height = image.len() / width;
+    let source = image.to_vec();
+    map_pixels(image, width, |x, y, color| {
+        let (dx, dy, _) = center_offset(x, y, width, height);
+        let sample = |scale: f32| {
+            let sx = (x as f32 + dx * offset * scale).round().clamp(0.0, (width - 1) as f32) as usize;
+            let sy = (y as f32 + dy * offset * scale).round().clamp(0.0, (height - 1) as f32) as usize;
+            source[sy * width + sx]
+        };
+        Color::from_f32(sample(1.0).r(), color.g(), sample(-1.0).b()).with_alpha(color.a())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 48;
+    const HEIGHT: usize = 32;
+
+    fn gradient() -> Vec<Color> {
+        (0..WIDTH * HEIGHT)
+            .map(|i| Color::from_f32((i % WIDTH) as f32 / WIDTH as f32, (i / WIDTH) as f32 / HEIGHT as f32, 0.5) * 0.8)
+            .collect()
+    }
+
+    #[test]
+    fn bloom_leaves_an_image_below_the_threshold_unchanged() {
+        let original = gradient();
+        let mut image = original.clone();
+        bloom(&mut image, WIDTH, 1.0, 2.0, 6.0);
+        assert_eq!(image, original);
+    }
+
+    #[test]
+    fn vignette_keeps_the_center_and_darkens_the_corners() {
+        let gray = Color::from_f32(0.6, 0.6, 0.6);
+        let mut image = vec![gray; WIDTH * HEIGHT];
+        vignette(&mut image, WIDTH, 0.8, 0.4);
+
+        let center = (HEIGHT / 2) * WIDTH + WIDTH / 2;
+        assert_eq!(image[center], gray);
+        for corner in [0, WIDTH - 1, (HEIGHT - 1) * WIDTH, HEIGHT * WIDTH - 1] {
+            assert!(image[corner].r() < gray.r() * 0.5, "esquina {corner}: {:?}", image[corner]);
+        }
+    }
+
+    #[test]
+    fn film_grain_is_deterministic_for_a_given_frame() {
+        let grain = |time: u32| {
+            let mut image = gradient();
+            film_grain(&mut image, WIDTH, 0.1, time);
+            image
+        };
+        assert_eq!(grain(7), grain(7));
+        assert_ne!(grain(7), grain(8));
+        assert_ne!(grain(7), gradient());
+    }
+}
@@ -0,0 +1,640 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use crate::framebuffer::{Framebuffer, GBuffer, TransparentDraw, NO_MATERIAL, read_rows, write_rows};
+use crate::fragment::{Fragment, fragment_shader};
+use crate::triangle::{triangle, triangle_multisample, triangle_points, wireframe_triangle, calculate_bounding_box};
+use crate::vertex::Vertex;
+use crate::line::{line, LineStyle};
+use crate::color::Color;
+use crate::pipeline::BlendMode;
+use crate::parallel::map_pixels;
+use crate::Uniforms;
+
+const TILE_SIZE: usize = 64;
+const OVERLAY_DEPTH_BIAS: f32 = 1e-4;
+const POINT_SIZE: i32 = 3;
+const WIREFRAME_COLOR: Color = Color::from_f32(1.0, 1.0, 1.0);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    fn bounds(&self) -> (i32, i32, i32, i32) {
+        (
+            self.x as i32,
+            self.y as i32,
+            (self.x + self.width) as i32 - 1,
+            (self.y + self.height) as i32 - 1,
+        )
+    }
+}
+
+// Copia local de la región del framebuffer que cubre un tile. Con MSAA, `color` y
+// `depth` guardan `samples` valores consecutivos por píxel
+struct TileBuffer {
+    tile: Tile,
+    samples: usize,
+    color: Vec<Color>,
+    depth: Vec<f32>,
+    gbuffer: Option<GBuffer>,
+}
+
+impl TileBuffer {
+    fn read(framebuffer: &Framebuffer, tile: Tile) -> Self {
+        let stride = framebuffer.width;
+        let samples = framebuffer.samples;
+        let (color, depth) = if samples > 1 {
+            (
+                read_rows(&framebuffer.sample_buffer, stride * samples, tile.x * samples, tile.y, tile.width * samples, tile.height),
+                read_rows(&framebuffer.sample_zbuffer, stride * samples, tile.x * samples, tile.y, tile.width * samples, tile.height),
+            )
+        } else {
+            (
+                read_rows(&framebuffer.hdr_buffer, stride, tile.x, tile.y, tile.width, tile.height),
+                read_rows(&framebuffer.zbuffer, stride, tile.x, tile.y, tile.width, tile.height),
+            )
+        };
+
+        TileBuffer {
+            tile,
+            samples,
+            color,
+            depth,
+            gbuffer: framebuffer
+                .gbuffer
+                .as_ref()
+                .map(|gbuffer| gbuffer.read_region(stride, tile.x, tile.y, tile.width, tile.height)),
+        }
+    }
+
+    fn write(&self, framebuffer: &mut Framebuffer) {
+        let (stride, tile, samples) = (framebuffer.width, self.tile, self.samples);
+        if samples > 1 {
+            write_rows(&mut framebuffer.sample_buffer, &self.color, stride * samples, tile.x * samples, tile.y, tile.width * samples);
+            write_rows(&mut framebuffer.sample_zbuffer, &self.depth, stride * samples, tile.x * samples, tile.y, tile.width * samples);
+        } else {
+            write_rows(&mut framebuffer.hdr_buffer, &self.color, stride, tile.x, tile.y, tile.width);
+            write_rows(&mut framebuffer.zbuffer, &self.depth, stride, tile.x, tile.y, tile.width);
+        }
+        if let (Some(target), Some(region)) = (framebuffer.gbuffer.as_mut(), self.gbuffer.as_ref()) {
+            target.write_region(stride, tile.x, tile.y, tile.width, region);
+        }
+    }
+
+    // Escribe un fragmento de línea o punto con su color plano, mezclado con el fondo
+    // según su cobertura. El sesgo de profundidad deja ver las aristas sobre la
+    // superficie sólida que comparte su profundidad
+    fn write_overlay(&mut self, fragment: &Fragment, coverage: f32) {
+        let (x, y) = (fragment.position.x as i64, fragment.position.y as i64);
+        let tile = self.tile;
+        if x < tile.x as i64 || y < tile.y as i64 || x >= (tile.x + tile.width) as i64 || y >= (tile.y + tile.height) as i64 {
+            return;
+        }
+        if fragment.depth.is_nan() {
+            return;
+        }
+
+        let local = (y as usize - tile.y) * tile.width + (x as usize - tile.x);
+        let mut written = false;
+
+        for sample in local * self.samples..(local + 1) * self.samples {
+            if fragment.depth <= self.depth[sample] + OVERLAY_DEPTH_BIAS {
+                self.color[sample] = self.color[sample].lerp(&fragment.color, coverage);
+                if coverage >= 0.5 {
+                    self.depth[sample] = self.depth[sample].min(fragment.depth);
+                }
+                written = true;
+            }
+        }
+
+        // El color ya es definitivo: la pasada diferida no debe sobrescribirlo
+        if let (true, Some(gbuffer)) = (written, self.gbuffer.as_mut()) {
+            gbuffer.material[local] = NO_MATERIAL;
+        }
+    }
+}
+
+pub fn worker_count() -> usize {
+    static WORKERS: OnceLock<usize> = OnceLock::new();
+    *WORKERS.get_or_init(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+}
+
+pub fn create_tiles(width: usize, height: usize) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(TILE_SIZE) {
+        for x in (0..width).step_by(TILE_SIZE) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: TILE_SIZE.min(width - x),
+                height: TILE_SIZE.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+// Asigna a cada tile los índices de las primitivas cuyo cuadro delimitador
+// (min_x, min_y, max_x, max_y) lo toca, conservando el orden de envío
+fn bin_bounds(width: usize, height: usize, bounds: impl Iterator<Item = (i32, i32, i32, i32)>) -> Vec<Vec<usize>> {
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let tiles_y = height.div_ceil(TILE_SIZE);
+    let mut bins = vec![Vec::new(); tiles_x * tiles_y];
+
+    for (index, (min_x, min_y, max_x, max_y)) in bounds.enumerate() {
+        if max_x < 0 || max_y < 0 || min_x >= width as i32 || min_y >= height as i32 {
+            continue;
+        }
+
+        let first_x = min_x.max(0) as usize / TILE_SIZE;
+        let first_y = min_y.max(0) as usize / TILE_SIZE;
+        let last_x = (max_x as usize).min(width - 1) / TILE_SIZE;
+        let last_y = (max_y as usize).min(height - 1) / TILE_SIZE;
+
+        for tile_y in first_y..=last_y {
+            for tile_x in first_x..=last_x {
+                bins[tile_y * tiles_x + tile_x].push(index);
+            }
+        }
+    }
+
+    bins
+}
+
+// El cuadro de cada triángulo se amplía `margin` píxeles (para puntos y líneas gruesas)
+fn bin_triangles(width: usize, height: usize, triangles: &[[Vertex; 3]], margin: i32) -> Vec<Vec<usize>> {
+    bin_bounds(width, height, triangles.iter().map(|tri| {
+        let (min_x, min_y, max_x, max_y) = calculate_bounding_box(
+            &tri[0].transformed_position,
+            &tri[1].transformed_position,
+            &tri[2].transformed_position,
+        );
+        (min_x - margin, min_y - margin, max_x + margin, max_y + margin)
+    }))
+}
+
+fn bin_lines(width: usize, height: usize, lines: &[[Vertex; 2]], margin: i32) -> Vec<Vec<usize>> {
+    bin_bounds(width, height, lines.iter().map(|segment| {
+        let (a, b) = (segment[0].transformed_position, segment[1].transformed_position);
+        (
+            a.x.min(b.x).floor() as i32 - margin,
+            a.y.min(b.y).floor() as i32 - margin,
+            a.x.max(b.x).ceil() as i32 + margin,
+            a.y.max(b.y).ceil() as i32 + margin,
+        )
+    }))
+}
+
+fn shade_tile(
+    tile_buffer: &mut TileBuffer,
+    bin: &[usize],
+    triangles: &[[Vertex; 3]],
+    uniforms: &Uniforms,
+    shader_type: &str,
+    material: u16,
+) {
+    if tile_buffer.samples > 1 {
+        shade_tile_multisample(tile_buffer, bin, triangles, uniforms, shader_type);
+        return;
+    }
+
+    let tile = tile_buffer.tile;
+    for &index in bin {
+        let tri = &triangles[index];
+        triangle(&tri[0], &tri[1], &tri[2], tile.bounds(), &mut |fragment| {
+            let x = fragment.position.x as usize - tile.x;
+            let y = fragment.position.y as usize - tile.y;
+            let local = y * tile.width + x;
+
+            // Early-z: descartamos el fragmento antes de ejecutar el shader
+            if tile_buffer.depth[local] <= fragment.depth {
+                return;
+            }
+
+            tile_buffer.depth[local] = fragment.depth;
+
+            // En modo diferido solo guardamos la superficie visible
+            if let Some(gbuffer) = tile_buffer.gbuffer.as_mut() {
+                gbuffer.store(local, &fragment, material);
+                return;
+            }
+
+            tile_buffer.color[local] = fragment_shader(&fragment, uniforms, shader_type);
+        });
+    }
+}
+
+// El fragment shader se ejecuta una vez por píxel y triángulo, y el color se copia
+// a las muestras cubiertas que pasan la prueba de profundidad
+fn shade_tile_multisample(
+    tile_buffer: &mut TileBuffer,
+    bin: &[usize],
+    triangles: &[[Vertex; 3]],
+    uniforms: &Uniforms,
+    shader_type: &str,
+) {
+    let (tile, samples) = (tile_buffer.tile, tile_buffer.samples);
+
+    for &index in bin {
+        let tri = &triangles[index];
+        triangle_multisample(&tri[0], &tri[1], &tri[2], tile.bounds(), samples, &mut |fragment, coverage, depths| {
+            let x = fragment.position.x as usize - tile.x;
+            let y = fragment.position.y as usize - tile.y;
+            let first = (y * tile.width + x) * samples;
+
+            let mut passed = 0u32;
+            for (sample, &depth) in depths.iter().enumerate() {
+                if coverage & (1 << sample) != 0 && tile_buffer.depth[first + sample] > depth {
+                    passed |= 1 << sample;
+                }
+            }
+            if passed == 0 {
+                return;
+            }
+
+            let color = fragment_shader(&fragment, uniforms, shader_type);
+            for (sample, &depth) in depths.iter().enumerate() {
+                if passed & (1 << sample) != 0 {
+                    tile_buffer.color[first + sample] = color;
+                    tile_buffer.depth[first + sample] = depth;
+                }
+            }
+        });
+    }
+}
+
+pub fn rasterize(framebuffer: &mut Framebuffer, triangles: &[[Vertex; 3]], uniforms: &Uniforms, shader_type: &str) {
+    rasterize_with_workers(framebuffer, triangles, uniforms, shader_type, worker_count());
+}
+
+// Con un solo worker los tiles se procesan en el hilo actual (ruta serial);
+// el resultado es idéntico porque cada tile respeta el orden de los triángulos
+pub fn rasterize_with_workers(
+    framebuffer: &mut Framebuffer,
+    triangles: &[[Vertex; 3]],
+    uniforms: &Uniforms,
+    shader_type: &str,
+    workers: usize,
+) {
+    let bins = bin_triangles(framebuffer.width, framebuffer.height, triangles, 0);
+    let material = if framebuffer.is_deferred() { framebuffer.material_id(shader_type) } else { NO_MATERIAL };
+
+    process_tiles(framebuffer, &bins, workers, |tile_buffer, bin| {
+        shade_tile(tile_buffer, bin, triangles, uniforms, shader_type, material);
+    });
+}
+
+// Fragmento translúcido en la lista de su píxel (transparencia independiente del orden).
+// `coverage` marca las muestras que pasaron la prueba de profundidad
+#[derive(Clone, Copy)]
+struct TransparentFragment {
+    depth: f32,
+    color: Color,
+    blend_mode: BlendMode,
+    coverage: u32,
+}
+
+impl TileBuffer {
+    fn blend_samples(&mut self, local: usize, coverage: u32, source: Color, blend_mode: BlendMode) {
+        let first = local * self.samples;
+        for sample in 0..self.samples {
+            if coverage & (1 << sample) != 0 {
+                self.color[first + sample] = blend_mode.blend(source, self.color[first + sample]);
+            }
+        }
+    }
+}
+
+// Mezcla un triángulo translúcido sobre el tile: prueba de profundidad sí, escritura no.
+// Con `fragment_lists`, los fragmentos se guardan por píxel en vez de mezclarse
+fn blend_triangle(
+    tile_buffer: &mut TileBuffer,
+    tri: &[Vertex; 3],
+    draw: &TransparentDraw,
+    uniforms: &Uniforms,
+    mut fragment_lists: Option<&mut [Vec<TransparentFragment>]>,
+) {
+    let (tile, samples) = (tile_buffer.tile, tile_buffer.samples);
+
+    let mut write = |tile_buffer: &mut TileBuffer, local: usize, coverage: u32, fragment: &Fragment| {
+        let source = fragment_shader(fragment, uniforms, &draw.shader_type);
+        match fragment_lists.as_deref_mut() {
+            Some(lists) => lists[local].push(TransparentFragment {
+                depth: fragment.depth,
+                color: source,
+                blend_mode: draw.blend_mode,
+                coverage,
+            }),
+            None => tile_buffer.blend_samples(local, coverage, source, draw.blend_mode),
+        }
+    };
+
+    if samples == 1 {
+        triangle(&tri[0], &tri[1], &tri[2], tile.bounds(), &mut |fragment| {
+            let x = fragment.position.x as usize - tile.x;
+            let y = fragment.position.y as usize - tile.y;
+            let local = y * tile.width + x;
+
+            if tile_buffer.depth[local] <= fragment.depth {
+                return;
+            }
+            write(tile_buffer, local, 1, &fragment);
+        });
+        return;
+    }
+
+    triangle_multisample(&tri[0], &tri[1], &tri[2], tile.bounds(), samples, &mut |fragment, coverage, depths| {
+        let x = fragment.position.x as usize - tile.x;
+        let y = fragment.position.y as usize - tile.y;
+        let local = y * tile.width + x;
+
+        let mut passed = 0u32;
+        for (sample, &depth) in depths.iter().enumerate() {
+            if coverage & (1 << sample) != 0 && tile_buffer.depth[local * samples + sample] > depth {
+                passed |= 1 << sample;
+            }
+        }
+        if passed != 0 {
+            write(tile_buffer, local, passed, &fragment);
+        }
+    });
+}
+
+// Ordena cada lista de atrás hacia adelante y la mezcla sobre el color opaco. El orden
+// estable conserva el orden de envío entre fragmentos a la misma profundidad
+fn resolve_fragment_lists(tile_buffer: &mut TileBuffer, fragment_lists: &mut [Vec<TransparentFragment>]) {
+    for (local, list) in fragment_lists.iter_mut().enumerate() {
+        list.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+        for fragment in list.iter() {
+            tile_buffer.blend_samples(local, fragment.coverage, fragment.color, fragment.blend_mode);
+        }
+    }
+}
+
+// Pasada transparente: se ejecuta después de la geometría opaca (y de shade_deferred).
+// Sin transparencia independiente del orden, los triángulos se ordenan de atrás hacia
+// adelante por la profundidad de su centroide y el orden se conserva en cada tile
+pub fn render_transparent(framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+    let mut draws = std::mem::take(&mut framebuffer.transparent);
+    if draws.is_empty() {
+        return;
+    }
+
+    let mut queued: Vec<(f32, usize, [Vertex; 3])> = Vec::new();
+    for (draw_index, draw) in draws.iter_mut().enumerate() {
+        for tri in draw.triangles.drain(..) {
+            let depth = (tri[0].transformed_position.z + tri[1].transformed_position.z + tri[2].transformed_position.z) / 3.0;
+            queued.push((depth, draw_index, tri));
+        }
+    }
+
+    let order_independent = framebuffer.is_order_independent();
+    if !order_independent {
+        queued.sort_by(|a, b| b.0.total_cmp(&a.0));
+    }
+
+    let (owners, triangles): (Vec<usize>, Vec<[Vertex; 3]>) = queued.into_iter().map(|(_, owner, tri)| (owner, tri)).unzip();
+    let bins = bin_triangles(framebuffer.width, framebuffer.height, &triangles, 0);
+
+    process_tiles(framebuffer, &bins, worker_count(), |tile_buffer, bin| {
+        let tile = tile_buffer.tile;
+        let mut fragment_lists = order_independent.then(|| vec![Vec::new(); tile.width * tile.height]);
+
+        for &index in bin {
+            blend_triangle(tile_buffer, &triangles[index], &draws[owners[index]], uniforms, fragment_lists.as_deref_mut());
+        }
+
+        if let Some(lists) = fragment_lists.as_mut() {
+            resolve_fragment_lists(tile_buffer, lists);
+        }
+    });
+}
+
+// Dibuja las aristas de cada triángulo con prueba de profundidad
+pub fn rasterize_wireframe(framebuffer: &mut Framebuffer, triangles: &[[Vertex; 3]]) {
+    let bins = bin_triangles(framebuffer.width, framebuffer.height, triangles, 1);
+    let style = LineStyle::default();
+
+    process_tiles(framebuffer, &bins, worker_count(), |tile_buffer, bin| {
+        for &index in bin {
+            let tri = &triangles[index];
+            wireframe_triangle(&tri[0], &tri[1], &tri[2], &style, &mut |mut fragment, coverage| {
+                fragment.color = WIREFRAME_COLOR;
+                tile_buffer.write_overlay(&fragment, coverage);
+            });
+        }
+    });
+}
+
+// Dibuja los vértices de cada triángulo como puntos de POINT_SIZE píxeles
+pub fn rasterize_points(framebuffer: &mut Framebuffer, triangles: &[[Vertex; 3]]) {
+    let bins = bin_triangles(framebuffer.width, framebuffer.height, triangles, POINT_SIZE);
+
+    process_tiles(framebuffer, &bins, worker_count(), |tile_buffer, bin| {
+        for &index in bin {
+            let tri = &triangles[index];
+            triangle_points(&tri[0], &tri[1], &tri[2], POINT_SIZE, &mut |fragment| tile_buffer.write_overlay(&fragment, 1.0));
+        }
+    });
+}
+
+// Segmentos en pantalla con el color interpolado de sus vértices
+pub fn rasterize_lines(framebuffer: &mut Framebuffer, lines: &[[Vertex; 2]], style: &LineStyle) {
+    let margin = style.thickness.ceil() as i32 + 1;
+    let bins = bin_lines(framebuffer.width, framebuffer.height, lines, margin);
+
+    process_tiles(framebuffer, &bins, worker_count(), |tile_buffer, bin| {
+        for &index in bin {
+            let segment = &lines[index];
+            line(&segment[0], &segment[1], style, &mut |fragment, coverage| tile_buffer.write_overlay(&fragment, coverage));
+        }
+    });
+}
+
+// Ejecuta `process` sobre cada tile con primitivas asignadas, repartiendo los tiles
+// entre `workers` hilos, y copia el resultado de vuelta al framebuffer. Los hilos con
+// ámbito pueden tomar prestados el framebuffer y los triángulos sin Arc ni 'static;
+// crearlos cuesta microsegundos por llamada, poco frente al trabajo de los tiles
+fn process_tiles<F>(framebuffer: &mut Framebuffer, bins: &[Vec<usize>], workers: usize, process: F)
+where
+    F: Fn(&mut TileBuffer, &[usize]) + Sync,
+{
+    let tiles = create_tiles(framebuffer.width, framebuffer.height);
+    let pending: Vec<usize> = (0..tiles.len()).filter(|&i| !bins[i].is_empty()).collect();
+
+    let run = |shared: &Framebuffer, i: usize| {
+        let mut tile_buffer = TileBuffer::read(shared, tiles[i]);
+        process(&mut tile_buffer, &bins[i]);
+        tile_buffer
+    };
+
+    let results: Vec<TileBuffer> = if workers <= 1 || pending.len() <= 1 {
+        pending.iter().map(|&i| run(framebuffer, i)).collect()
+    } else {
+        let next = AtomicUsize::new(0);
+        let shared: &Framebuffer = framebuffer;
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers.min(pending.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut finished = Vec::new();
+                        loop {
+                            let n = next.fetch_add(1, Ordering::Relaxed);
+                            if n >= pending.len() {
+                                break;
+                            }
+                            finished.push(run(shared, pending[n]));
+                        }
+                        finished
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("rasterizer worker panicked"))
+                .collect()
+        })
+    };
+
+    for tile_buffer in &results {
+        tile_buffer.write(framebuffer);
+    }
+}
+
+// Pasada de sombreado diferido: ejecuta el fragment shader una sola vez por píxel visible
+pub fn shade_deferred(framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+    if !framebuffer.is_deferred() {
+        return;
+    }
+
+    let width = framebuffer.width;
+    let mut buffer = std::mem::take(&mut framebuffer.hdr_buffer);
+    let shared: &Framebuffer = framebuffer;
+
+    if let Some(gbuffer) = shared.gbuffer.as_ref() {
+        map_pixels(&mut buffer, width, |x, y, color| {
+            let index = y * width + x;
+            let material = gbuffer.material[index];
+            if material == NO_MATERIAL {
+                return color;
+            }
+
+            let fragment = gbuffer.fragment(index, x, y, shared.zbuffer[index]);
+            fragment_shader(&fragment, uniforms, shared.material_name(material))
+        });
+    }
+
+    framebuffer.hdr_buffer = buffer;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Vec2, Vec3};
+    use std::collections::HashSet;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn parallel_tiles_match_the_serial_path_bit_for_bit() {
+        let (width, height) = (200, 150);
+        let mut rng = StdRng::seed_from_u64(11);
+
+        // Triángulos grandes y solapados, con profundidades distintas, que cruzan varios tiles
+        let triangles: Vec<[Vertex; 3]> = (0..60)
+            .map(|_| {
+                [0; 3].map(|_| {
+                    let position = Vec3::new(
+                        rng.gen_range(-20.0..width as f32 + 20.0),
+                        rng.gen_range(-20.0..height as f32 + 20.0),
+                        rng.gen_range(0.1..0.9),
+                    );
+                    Vertex::new(position, Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0))
+                })
+            })
+            .collect();
+
+        // La superficie de la estrella varía con la posición interpolada, así que la comparación
+        // de color también comprueba la interpolación de cada fragmento
+        let uniforms = crate::create_uniforms();
+        let render = |workers: usize| {
+            let mut framebuffer = Framebuffer::new(width, height);
+            framebuffer.clear();
+            rasterize_with_workers(&mut framebuffer, &triangles, &uniforms, "solar_surface", workers);
+            framebuffer
+        };
+        let (serial, parallel) = (render(1), render(4));
+
+        let color_bits = |framebuffer: &Framebuffer| -> Vec<[u32; 4]> {
+            framebuffer.hdr_buffer.iter()
+                .map(|c| [c.r().to_bits(), c.g().to_bits(), c.b().to_bits(), c.a().to_bits()])
+                .collect()
+        };
+        let depth_bits = |framebuffer: &Framebuffer| -> Vec<u32> {
+            framebuffer.zbuffer.iter().map(|depth| depth.to_bits()).collect()
+        };
+
+        let covered: HashSet<[u32; 4]> = color_bits(&serial)
+            .into_iter()
+            .zip(&serial.zbuffer)
+            .filter(|(_, depth)| depth.is_finite())
+            .map(|(color, _)| color)
+            .collect();
+        assert!(covered.len() > 1, "{} colores distintos", covered.len());
+        assert_eq!(color_bits(&serial), color_bits(&parallel));
+        assert_eq!(depth_bits(&serial), depth_bits(&parallel));
+    }
+
+    // Dos triángulos translúcidos que se solapan en el centro, uno detrás del otro; se
+    // encolan como draws separados en el orden pedido y se devuelve el píxel central
+    fn overlapping_translucent_pixel(order_independent: bool, back_first: bool, with_back: bool) -> [u32; 4] {
+        let (width, height) = (64, 64);
+        let at_depth = |z: f32| -> [Vertex; 3] {
+            [(4.0, 4.0), (60.0, 8.0), (32.0, 60.0)]
+                .map(|(x, y)| Vertex::new(Vec3::new(x, y, z), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0)))
+        };
+        let (back, front) = (at_depth(0.8), at_depth(0.3));
+
+        let uniforms = crate::create_uniforms();
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.clear();
+        framebuffer.set_order_independent(order_independent);
+
+        let mut draws = vec![front];
+        if with_back {
+            draws.insert(if back_first { 0 } else { 1 }, back);
+        }
+        for tri in &draws {
+            framebuffer.queue_transparent(std::slice::from_ref(tri), "ring", BlendMode::Alpha);
+        }
+        render_transparent(&mut framebuffer, &uniforms);
+
+        let c = framebuffer.hdr_buffer[(height / 2) * width + width / 2];
+        [c.r().to_bits(), c.g().to_bits(), c.b().to_bits(), c.a().to_bits()]
+    }
+
+    #[test]
+    fn sorted_transparency_does_not_depend_on_submission_order() {
+        let back_first = overlapping_translucent_pixel(false, true, true);
+        assert_eq!(back_first, overlapping_translucent_pixel(false, false, true));
+        // El triángulo de atrás tiene que notarse a través del de adelante
+        assert_ne!(back_first, overlapping_translucent_pixel(false, true, false));
+    }
+
+    #[test]
+    fn fragment_lists_do_not_depend_on_submission_order() {
+        let back_first = overlapping_translucent_pixel(true, true, true);
+        assert_eq!(back_first, overlapping_translucent_pixel(true, false, true));
+        assert_ne!(back_first, overlapping_translucent_pixel(true, true, false));
+        // Las listas por píxel resuelven lo mismo que el orden por centroides
+        assert_eq!(back_first, overlapping_translucent_pixel(false, true, true));
+    }
+}
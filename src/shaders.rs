@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec3, Vec4};
+use nalgebra_glm::{Vec3, Vec4, Mat3, Mat4};
 use crate::vertex::Vertex;
 use crate::Uniforms;
 
@@ -9,22 +9,35 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     vertex.position.z,
     1.0
   );
-  let transformed = uniforms.model_matrix * position;
+  let world_position = uniforms.model_matrix * position;
 
-  let w = transformed.w;
-  let transformed_position = Vec3::new(
-    transformed.x / w,
-    transformed.y / w,
-    transformed.z / w
-  );
+  // La división de perspectiva se hace después del recorte
+  let clip_position = uniforms.projection_matrix
+    * uniforms.view_matrix
+    * world_position;
 
+  // Normal en espacio de mundo, para que la luz de la estrella no gire con el planeta
+  let world_normal = (normal_matrix(&uniforms.model_matrix) * vertex.normal)
+    .try_normalize(f32::EPSILON)
+    .unwrap_or_else(Vec3::zeros);
 
   Vertex {
     position: vertex.position,
     normal: vertex.normal,
     tex_coords: vertex.tex_coords,
     color: vertex.color,
-    transformed_position,
-    transformed_normal: vertex.normal,
+    world_position: Vec3::new(world_position.x, world_position.y, world_position.z),
+    clip_position,
+    transformed_position: vertex.transformed_position,
+    transformed_normal: world_normal,
   }
-}
\ No newline at end of file
+}
+
+// Inversa transpuesta de la parte 3x3 del modelo: mantiene las normales perpendiculares
+// a la superficie aunque la escala no sea uniforme
+pub fn normal_matrix(model_matrix: &Mat4) -> Mat3 {
+  let linear = model_matrix.fixed_view::<3, 3>(0, 0).into_owned();
+  linear.try_inverse()
+    .map(|inverse| inverse.transpose())
+    .unwrap_or(linear)
+}
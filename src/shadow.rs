@@ -0,0 +1,173 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4, look_at};
+use crate::obj::IndexedMesh;
+use crate::triangle::edge_function;
+
+// Profundidad de la escena vista desde la estrella. La estrella está lejos comparada
+// con el tamaño del sistema, así que se usa una proyección ortográfica en la dirección
+// de la luz que cubre una esfera de `radius` alrededor de `target`
+pub struct ShadowMap {
+    pub size: usize,
+    pub depth: Vec<f32>,
+    light_view: Mat4,
+    radius: f32,
+    // Con PCF se promedian (2 * pcf_radius + 1)^2 comparaciones alrededor del texel
+    pub pcf_radius: i32,
+}
+
+impl ShadowMap {
+    pub fn new(size: usize, light_position: Vec3, target: Vec3, radius: f32) -> Self {
+        let direction = (target - light_position).normalize();
+        let up = if direction.y.abs() > 0.99 { Vec3::new(0.0, 0.0, 1.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+
+        ShadowMap {
+            size,
+            depth: vec![f32::INFINITY; size * size],
+            light_view: look_at(&light_position, &target, &up),
+            radius,
+            pcf_radius: 2,
+        }
+    }
+
+    // Dirección desde la escena hacia la luz
+    pub fn light_direction(&self) -> Vec3 {
+        let forward = self.light_view.row(2);
+        Vec3::new(forward[0], forward[1], forward[2])
+    }
+
+    // Posición en texels (x, y) y distancia a la luz de un punto del mundo
+    fn project(&self, world_position: &Vec3) -> Vec3 {
+        let view = self.light_view * Vec4::new(world_position.x, world_position.y, world_position.z, 1.0);
+        let texels_per_unit = self.size as f32 / (2.0 * self.radius);
+        Vec3::new(
+            (view.x + self.radius) * texels_per_unit,
+            (self.radius - view.y) * texels_per_unit,
+            -view.z,
+        )
+    }
+
+    // Pasada de solo profundidad. No hay culling: las dos caras de un triángulo proyectan sombra
+    pub fn render(&mut self, mesh: &IndexedMesh, model_matrix: &Mat4) {
+        let projected: Vec<Vec3> = mesh.vertices.iter()
+            .map(|vertex| {
+                let world = model_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+                self.project(&Vec3::new(world.x, world.y, world.z))
+            })
+            .collect();
+
+        for indices in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| projected[indices[i] as usize]);
+            self.rasterize_depth(&a, &b, &c);
+        }
+    }
+
+    fn rasterize_depth(&mut self, a: &Vec3, b: &Vec3, c: &Vec3) {
+        let area = edge_function(a, b, c);
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+
+        let last = self.size as f32 - 1.0;
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as usize;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as usize;
+        let max_x = a.x.max(b.x).max(c.x).ceil().min(last);
+        let max_y = a.y.max(b.y).max(c.y).ceil().min(last);
+        if max_x < 0.0 || max_y < 0.0 {
+            return;
+        }
+
+        for y in min_y..=max_y as usize {
+            for x in min_x..=max_x as usize {
+                let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+                let w1 = edge_function(b, c, &point) / area;
+                let w2 = edge_function(c, a, &point) / area;
+                let w3 = edge_function(a, b, &point) / area;
+                if w1 < 0.0 || w2 < 0.0 || w3 < 0.0 {
+                    continue;
+                }
+
+                // La proyección es ortográfica, así que la profundidad se interpola linealmente
+                let depth = a.z * w1 + b.z * w2 + c.z * w3;
+                let texel = &mut self.depth[y * self.size + x];
+                if depth < *texel {
+                    *texel = depth;
+                }
+            }
+        }
+    }
+
+    // Fracción de luz que llega al punto: 1.0 iluminado, 0.0 en sombra. El punto se
+    // desplaza sobre la normal y el sesgo crece con la inclinación respecto a la luz
+    // para evitar que la superficie se sombree a sí misma (acne)
+    pub fn factor(&self, world_position: &Vec3, normal: &Vec3) -> f32 {
+        let texel_size = 2.0 * self.radius / self.size as f32;
+        let light_direction = self.light_direction();
+        // Superficies de dos caras (los anillos): la normal se orienta hacia la luz
+        let normal = if normal.dot(&light_direction) < 0.0 { -normal } else { *normal };
+        let cos_theta = normal.dot(&light_direction).clamp(0.05, 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let slope = sin_theta / cos_theta;
+
+        // Cerca del terminador las facetas de la malla se separan más de la superficie suave
+        let offset_position = world_position + normal * texel_size * (1.0 + 3.0 * sin_theta);
+        let projected = self.project(&offset_position);
+        let bias = texel_size * (1.0 + slope.min(4.0));
+
+        let (center_x, center_y) = (projected.x.floor() as i32, projected.y.floor() as i32);
+        let mut lit = 0;
+        let mut taps = 0;
+        for dy in -self.pcf_radius..=self.pcf_radius {
+            for dx in -self.pcf_radius..=self.pcf_radius {
+                let (x, y) = (center_x + dx, center_y + dy);
+                taps += 1;
+                // Fuera del mapa no hay nada que proyecte sombra
+                if x < 0 || y < 0 || x >= self.size as i32 || y >= self.size as i32 {
+                    lit += 1;
+                    continue;
+                }
+                if projected.z - bias <= self.depth[y as usize * self.size + x as usize] {
+                    lit += 1;
+                }
+            }
+        }
+
+        lit as f32 / taps as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+    use crate::vertex::Vertex;
+
+    // Luz sobre el origen y un cuadrado opaco de 2x2 a media altura que tapa el centro
+    fn shadow_map_with_occluder() -> ShadowMap {
+        let corner = |x: f32, z: f32| Vertex::new(Vec3::new(x, 2.0, z), Vec3::new(0.0, 1.0, 0.0), Vec2::new(0.0, 0.0));
+        let occluder = IndexedMesh {
+            vertices: vec![corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)],
+            indices: vec![0, 1, 2, 0, 2, 3],
+        };
+
+        let mut shadow_map = ShadowMap::new(256, Vec3::new(0.0, 10.0, 0.0), Vec3::zeros(), 4.0);
+        shadow_map.render(&occluder, &Mat4::identity());
+        shadow_map
+    }
+
+    #[test]
+    fn occluded_points_are_in_shadow_and_open_ones_are_lit() {
+        let shadow_map = shadow_map_with_occluder();
+        let up = Vec3::new(0.0, 1.0, 0.0);
+
+        assert!(shadow_map.factor(&Vec3::new(0.0, 0.0, 0.0), &up) < 0.01);
+        assert_eq!(shadow_map.factor(&Vec3::new(3.0, 0.0, 0.0), &up), 1.0);
+        // El propio oclusor no se sombrea a sí mismo
+        assert_eq!(shadow_map.factor(&Vec3::new(0.0, 2.0, 0.0), &up), 1.0);
+    }
+
+    #[test]
+    fn pcf_gives_a_penumbra_at_the_shadow_edge() {
+        let shadow_map = shadow_map_with_occluder();
+        let factor = shadow_map.factor(&Vec3::new(1.0, 0.0, 0.3), &Vec3::new(0.0, 1.0, 0.0));
+        assert!(factor > 0.2 && factor < 0.8, "{}", factor);
+    }
+}
@@ -0,0 +1,366 @@
+use std::f32::consts::PI;
+use nalgebra_glm::{Vec3, Vec4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType, DomainWarpType};
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::parallel::map_pixels;
+use crate::Uniforms;
+
+// Magnitudes aparentes de las estrellas: 0 las más brillantes, 6 el límite a simple vista
+const BRIGHTEST_MAGNITUDE: f32 = 0.0;
+const FAINTEST_MAGNITUDE: f32 = 6.0;
+
+// Colores según la temperatura, de las estrellas azules a las rojizas, con su frecuencia
+const STAR_COLORS: [(f32, Color); 5] = [
+    (0.15, Color::from_f32(0.7, 0.8, 1.0)),
+    (0.3, Color::from_f32(1.0, 1.0, 1.0)),
+    (0.3, Color::from_f32(1.0, 0.95, 0.82)),
+    (0.15, Color::from_f32(1.0, 0.85, 0.6)),
+    (0.1, Color::from_f32(1.0, 0.65, 0.45)),
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Star {
+    // Dirección en espacio de mundo: las estrellas están en el infinito
+    direction: Vec3,
+    color: Color,
+    brightness: f32,
+    twinkle_phase: f32,
+    twinkle_speed: f32,
+}
+
+// Campo de estrellas sobre una esfera celeste fija en el mundo. Solo la rotación de la
+// cámara lo mueve; acercarse o alejarse no cambia el fondo
+pub struct Starfield {
+    stars: Vec<Star>,
+    // Variación máxima del brillo al titilar (0.0 las deja fijas)
+    pub twinkle: f32,
+}
+
+impl Starfield {
+    // El mismo `seed` genera siempre el mismo cielo
+    pub fn new(seed: u64, count: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let stars = (0..count).map(|_| random_star(&mut rng)).collect();
+        Starfield { stars, twinkle: 0.35 }
+    }
+
+    // Proyecta cada estrella con la rotación de la cámara y aplica el titileo del instante actual
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        let time = uniforms.time as f32;
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+
+        for star in &self.stars {
+            // Con w = 0 solo se aplica la rotación de la vista
+            let view = uniforms.view_matrix * Vec4::new(star.direction.x, star.direction.y, star.direction.z, 0.0);
+            if view.z >= 0.0 {
+                continue;
+            }
+            let clip = uniforms.projection_matrix * Vec4::new(view.x, view.y, view.z, 1.0);
+            let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+            let screen = uniforms.viewport_matrix * ndc;
+            if screen.x < -1.0 || screen.y < -1.0 || screen.x > width || screen.y > height {
+                continue;
+            }
+
+            let twinkle = 1.0 + self.twinkle * (time * star.twinkle_speed + star.twinkle_phase).sin();
+            let color = star.color * (star.brightness * twinkle);
+
+            // Reparte la estrella entre los cuatro píxeles vecinos para que se mueva
+            // de forma continua al girar la cámara
+            let (x, y) = (screen.x - 0.5, screen.y - 0.5);
+            let (left, top) = (x.floor(), y.floor());
+            let (fraction_x, fraction_y) = (x - left, y - top);
+            for (dx, dy, weight) in [
+                (0, 0, (1.0 - fraction_x) * (1.0 - fraction_y)),
+                (1, 0, fraction_x * (1.0 - fraction_y)),
+                (0, 1, (1.0 - fraction_x) * fraction_y),
+                (1, 1, fraction_x * fraction_y),
+            ] {
+                let (pixel_x, pixel_y) = (left as i32 + dx, top as i32 + dy);
+                if pixel_x >= 0 && pixel_y >= 0 {
+                    framebuffer.add_background(pixel_x as usize, pixel_y as usize, color * weight);
+                }
+            }
+        }
+    }
+}
+
+fn random_star(rng: &mut StdRng) -> Star {
+    // Dirección uniforme sobre la esfera
+    let z: f32 = rng.gen_range(-1.0..1.0);
+    let phi = rng.gen_range(0.0..2.0 * PI);
+    let radius = (1.0 - z * z).sqrt();
+    let direction = Vec3::new(radius * phi.cos(), radius * phi.sin(), z);
+
+    // El número de estrellas crece como 10^(0.5 m): abundan las débiles
+    let (faint, bright) = (10f32.powf(0.5 * FAINTEST_MAGNITUDE), 10f32.powf(0.5 * BRIGHTEST_MAGNITUDE));
+    let magnitude = 2.0 * (bright + rng.gen::<f32>() * (faint - bright)).log10();
+    // Escala comprimida respecto a la real (10^(-0.4 m)) para que las débiles sigan visibles
+    let brightness = 2.5 * 10f32.powf(-0.24 * magnitude);
+
+    let mut pick = rng.gen::<f32>();
+    let mut color = STAR_COLORS[0].1;
+    for &(frequency, star_color) in &STAR_COLORS {
+        color = star_color;
+        if pick < frequency {
+            break;
+        }
+        pick -= frequency;
+    }
+
+    Star {
+        direction,
+        color,
+        brightness,
+        twinkle_phase: rng.gen_range(0.0..2.0 * PI),
+        twinkle_speed: rng.gen_range(0.05..0.2),
+    }
+}
+
+// Colores del fondo: la banda de la galaxia y el degradado de las nebulosas, del borde
+// de las nubes a su parte más densa. Valores lineales, pensados para quedar bajo las estrellas
+#[derive(Debug, Clone, Copy)]
+pub struct NebulaPalette {
+    pub band: Color,
+    pub outer: Color,
+    pub inner: Color,
+}
+
+impl NebulaPalette {
+    // Nebulosas de emisión: hidrógeno rojizo rodeado de oxígeno verde azulado
+    pub const EMISSION: NebulaPalette = NebulaPalette {
+        band: Color::from_f32(0.55, 0.5, 0.45),
+        outer: Color::from_f32(0.08, 0.22, 0.4),
+        inner: Color::from_f32(0.85, 0.2, 0.4),
+    };
+    // Nebulosas de reflexión: polvo que refleja la luz azul de estrellas cercanas
+    pub const REFLECTION: NebulaPalette = NebulaPalette {
+        band: Color::from_f32(0.5, 0.5, 0.55),
+        outer: Color::from_f32(0.05, 0.08, 0.3),
+        inner: Color::from_f32(0.35, 0.6, 1.0),
+    };
+}
+
+// Lado en texels de cada cara del cubemap donde se guarda el ruido del cielo
+const NEBULA_FACE_SIZE: usize = 256;
+
+// Valores del ruido en una dirección del cielo, todos entre 0 y 1
+#[derive(Debug, Clone, Copy, Default)]
+struct SkySample {
+    // fbm con el dominio deformado: forma de las nebulosas
+    cloud: f32,
+    // Intensidad de la banda de la galaxia, ya moteada
+    band: f32,
+    // Nubes de polvo que oscurecen la banda
+    dust: f32,
+}
+
+// Fondo de nebulosas y banda galáctica sobre la misma esfera celeste que las estrellas.
+// El ruido solo depende de la dirección, así que se evalúa una vez en un cubemap; la
+// paleta y la densidad se aplican al dibujar y pueden cambiarse en cualquier momento
+pub struct Nebula {
+    pub palette: NebulaPalette,
+    // Fracción del cielo cubierta por nebulosas, de 0.0 a 1.0
+    pub density: f32,
+    pub brightness: f32,
+    faces: Vec<SkySample>,
+}
+
+impl Nebula {
+    pub fn new(seed: i32, palette: NebulaPalette, density: f32) -> Self {
+        Nebula {
+            palette,
+            density: density.clamp(0.0, 1.0),
+            brightness: 1.0,
+            faces: bake_cubemap(seed),
+        }
+    }
+
+    // Reconstruye la dirección de vista de cada píxel y aplica la paleta al cubemap en ella
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        // Solo cuenta la rotación de la vista: el cielo está en el infinito
+        let mut view_rotation = uniforms.view_matrix;
+        view_rotation.fixed_view_mut::<3, 1>(0, 3).fill(0.0);
+        let Some(inverse) = (uniforms.projection_matrix * view_rotation).try_inverse() else {
+            return;
+        };
+
+        let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+        framebuffer.add_background_with(|x, y| {
+            let ndc_x = 2.0 * (x as f32 + 0.5) / width - 1.0;
+            let ndc_y = 1.0 - 2.0 * (y as f32 + 0.5) / height;
+            let far = inverse * Vec4::new(ndc_x, ndc_y, 1.0, 1.0);
+            let direction = Vec3::new(far.x, far.y, far.z).normalize();
+            self.shade(&self.sample(&direction))
+        });
+    }
+
+    fn shade(&self, sample: &SkySample) -> Color {
+        // La densidad baja el umbral a partir del cual el ruido se convierte en nube
+        let cloud = ((sample.cloud - (1.0 - self.density)) / self.density.max(1e-3)).clamp(0.0, 1.0);
+        let cloud = cloud * cloud * cloud;
+        let nebula = self.palette.outer.lerp(&self.palette.inner, cloud) * (cloud * 0.35);
+
+        let band = self.palette.band * (sample.band * (1.0 - 0.9 * sample.dust) * 0.1);
+        (nebula + band) * self.brightness
+    }
+
+    // Lectura bilineal dentro de la cara del cubemap que contiene la dirección
+    fn sample(&self, direction: &Vec3) -> SkySample {
+        let (face, u, v) = cube_face(direction);
+        let last = NEBULA_FACE_SIZE as f32 - 1.0;
+        let x = ((u * 0.5 + 0.5) * NEBULA_FACE_SIZE as f32 - 0.5).clamp(0.0, last);
+        let y = ((v * 0.5 + 0.5) * NEBULA_FACE_SIZE as f32 - 0.5).clamp(0.0, last);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(NEBULA_FACE_SIZE - 1), (y0 + 1).min(NEBULA_FACE_SIZE - 1));
+        let (fraction_x, fraction_y) = (x - x0 as f32, y - y0 as f32);
+
+        let texel = |x: usize, y: usize| self.faces[(face * NEBULA_FACE_SIZE + y) * NEBULA_FACE_SIZE + x];
+        let lerp = |a: SkySample, b: SkySample, t: f32| SkySample {
+            cloud: a.cloud + (b.cloud - a.cloud) * t,
+            band: a.band + (b.band - a.band) * t,
+            dust: a.dust + (b.dust - a.dust) * t,
+        };
+        lerp(
+            lerp(texel(x0, y0), texel(x1, y0), fraction_x),
+            lerp(texel(x0, y1), texel(x1, y1), fraction_x),
+            fraction_y,
+        )
+    }
+}
+
+// Cara del cubo (0..6) que atraviesa la dirección y coordenadas (u, v) en [-1, 1] sobre ella
+fn cube_face(direction: &Vec3) -> (usize, f32, f32) {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+    if ax >= ay && ax >= az {
+        if x > 0.0 { (0, -z / ax, -y / ax) } else { (1, z / ax, -y / ax) }
+    } else if ay >= az {
+        if y > 0.0 { (2, x / ay, z / ay) } else { (3, x / ay, -z / ay) }
+    } else if z > 0.0 {
+        (4, x / az, -y / az)
+    } else {
+        (5, -x / az, -y / az)
+    }
+}
+
+// Dirección que corresponde a las coordenadas (u, v) de una cara; inversa de `cube_face`
+fn cube_direction(face: usize, u: f32, v: f32) -> Vec3 {
+    let direction = match face {
+        0 => Vec3::new(1.0, -v, -u),
+        1 => Vec3::new(-1.0, -v, u),
+        2 => Vec3::new(u, 1.0, v),
+        3 => Vec3::new(u, -1.0, -v),
+        4 => Vec3::new(u, -v, 1.0),
+        _ => Vec3::new(-u, -v, -1.0),
+    };
+    direction.normalize()
+}
+
+fn bake_cubemap(seed: i32) -> Vec<SkySample> {
+    let mut cloud_noise = FastNoiseLite::with_seed(seed);
+    cloud_noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+    cloud_noise.set_fractal_type(Some(FractalType::FBm));
+    cloud_noise.set_fractal_octaves(Some(5));
+    cloud_noise.set_frequency(Some(1.2));
+
+    // La deformación del dominio estira el fbm en filamentos
+    let mut warp_noise = FastNoiseLite::with_seed(seed + 1);
+    warp_noise.set_domain_warp_type(Some(DomainWarpType::OpenSimplex2));
+    warp_noise.set_fractal_type(Some(FractalType::DomainWarpProgressive));
+    warp_noise.set_fractal_octaves(Some(3));
+    warp_noise.set_domain_warp_amp(Some(0.6));
+    warp_noise.set_frequency(Some(1.0));
+
+    let mut dust_noise = FastNoiseLite::with_seed(seed + 2);
+    dust_noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+    dust_noise.set_fractal_type(Some(FractalType::FBm));
+    dust_noise.set_fractal_octaves(Some(4));
+    dust_noise.set_frequency(Some(4.0));
+
+    // Polo de la galaxia inclinado para que la banda cruce el cielo en diagonal
+    let galactic_pole = Vec3::new(0.35, 1.0, 0.25).normalize();
+    let band_width = 0.22;
+
+    // Las seis caras van una debajo de otra: la fila `row` pertenece a la cara row / lado
+    let mut faces = vec![SkySample::default(); 6 * NEBULA_FACE_SIZE * NEBULA_FACE_SIZE];
+    map_pixels(&mut faces, NEBULA_FACE_SIZE, |x, row, _| {
+        let (face, y) = (row / NEBULA_FACE_SIZE, row % NEBULA_FACE_SIZE);
+        let u = (x as f32 + 0.5) / NEBULA_FACE_SIZE as f32 * 2.0 - 1.0;
+        let v = (y as f32 + 0.5) / NEBULA_FACE_SIZE as f32 * 2.0 - 1.0;
+        let direction = cube_direction(face, u, v);
+
+        let (wx, wy, wz) = warp_noise.domain_warp_3d(direction.x, direction.y, direction.z);
+        let cloud = cloud_noise.get_noise_3d(wx, wy, wz) * 0.5 + 0.5;
+
+        let dust = dust_noise.get_noise_3d(direction.x, direction.y, direction.z) * 0.5 + 0.5;
+        let latitude = direction.dot(&galactic_pole).asin();
+        let band = (-(latitude / band_width).powi(2)).exp() * (1.6 * cloud - 0.3).clamp(0.0, 1.0);
+
+        SkySample { cloud, band, dust: (dust * 1.6 - 0.5).clamp(0.0, 1.0) }
+    });
+
+    faces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_generates_the_same_sky() {
+        let (a, b, c) = (Starfield::new(7, 100), Starfield::new(7, 100), Starfield::new(8, 100));
+        let directions = |starfield: &Starfield| starfield.stars.iter().map(|star| star.direction).collect::<Vec<_>>();
+
+        assert_eq!(directions(&a), directions(&b));
+        assert_ne!(directions(&a), directions(&c));
+    }
+
+    #[test]
+    fn faint_stars_outnumber_bright_ones() {
+        let starfield = Starfield::new(1337, 2000);
+        let bright = starfield.stars.iter().filter(|star| star.brightness > 1.0).count();
+        let faint = starfield.stars.iter().filter(|star| star.brightness < 0.3).count();
+
+        assert!(faint > bright * 10, "{} débiles, {} brillantes", faint, bright);
+    }
+
+    // Sin cubemap: estas pruebas solo miran cómo se colorea una muestra del ruido
+    fn nebula(palette: NebulaPalette, density: f32) -> Nebula {
+        Nebula { palette, density, brightness: 1.0, faces: Vec::new() }
+    }
+
+    #[test]
+    fn zero_density_leaves_no_nebula() {
+        let dense_cloud = SkySample { cloud: 0.95, band: 0.0, dust: 0.0 };
+
+        assert!(nebula(NebulaPalette::EMISSION, 0.0).shade(&dense_cloud).is_black());
+        assert!(!nebula(NebulaPalette::EMISSION, 0.45).shade(&dense_cloud).is_black());
+    }
+
+    #[test]
+    fn palette_changes_the_nebula_and_band_colors() {
+        for sample in [
+            SkySample { cloud: 0.9, band: 0.0, dust: 0.0 },
+            SkySample { cloud: 0.0, band: 0.8, dust: 0.2 },
+        ] {
+            let emission = nebula(NebulaPalette::EMISSION, 0.45).shade(&sample);
+            let reflection = nebula(NebulaPalette::REFLECTION, 0.45).shade(&sample);
+            assert_ne!(emission, reflection);
+        }
+    }
+
+    #[test]
+    fn cube_faces_round_trip() {
+        for face in 0..6 {
+            for (u, v) in [(0.0, 0.0), (0.5, -0.25), (-0.9, 0.8)] {
+                let (found, found_u, found_v) = cube_face(&cube_direction(face, u, v));
+                assert_eq!(found, face);
+                assert!((found_u - u).abs() < 1e-5 && (found_v - v).abs() < 1e-5, "{} {} {}", face, found_u, found_v);
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::color::Color;
+
+// Operadores que llevan el color HDR del framebuffer al rango [0, 1] de la pantalla
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapper {
+    // Recorta cada canal a 1.0, como antes de tener un framebuffer HDR
+    Clamp,
+    Reinhard,
+    // Aproximación de Krzysztof Narkowicz a la curva fílmica ACES
+    Aces,
+}
+
+impl ToneMapper {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapper::Clamp => ToneMapper::Reinhard,
+            ToneMapper::Reinhard => ToneMapper::Aces,
+            ToneMapper::Aces => ToneMapper::Clamp,
+        }
+    }
+
+    // `exposure` escala la luz antes de comprimirla
+    pub fn apply(self, color: Color, exposure: f32) -> Color {
+        let color = color * exposure;
+        match self {
+            ToneMapper::Clamp => color.map(|x| x.min(1.0)),
+            ToneMapper::Reinhard => color.map(reinhard),
+            ToneMapper::Aces => color.map(aces),
+        }
+    }
+}
+
+pub fn reinhard(x: f32) -> f32 {
+    x / (1.0 + x)
+}
+
+pub fn aces(x: f32) -> f32 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    ((x * (a * x + b)) / (x * (c * x + d) + e)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reinhard_maps_one_to_one_half() {
+        assert_eq!(reinhard(1.0), 0.5);
+        assert_eq!(ToneMapper::Reinhard.apply(Color::from_f32(1.0, 1.0, 1.0), 1.0), Color::from_f32(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn aces_is_monotonic_and_never_exceeds_one() {
+        let samples: Vec<f32> = (0..=2000).map(|i| aces(i as f32 * 0.05)).collect();
+        assert!(samples.windows(2).all(|pair| pair[0] <= pair[1]));
+        for x in [10.0, 100.0, 1.0e4, 1.0e8] {
+            assert!(aces(x) <= 1.0, "aces({x}) = {}", aces(x));
+        }
+    }
+
+    #[test]
+    fn exposure_scales_the_input_before_the_curve() {
+        let color = Color::from_f32(0.25, 0.5, 1.0);
+        for mapper in [ToneMapper::Clamp, ToneMapper::Reinhard, ToneMapper::Aces] {
+            assert_eq!(mapper.apply(color, 2.0), mapper.apply(color * 2.0, 1.0));
+        }
+        // Con Reinhard, escalar después de la curva daría 4/3 en vez de 4/5
+        assert_eq!(ToneMapper::Reinhard.apply(Color::from_f32(2.0, 2.0, 2.0), 2.0).r(), 0.8);
+    }
+}
@@ -1,65 +1,286 @@
-use nalgebra_glm::{Vec2, Vec3, dot};
+use nalgebra_glm::{Vec2, Vec3};
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::line::line;
+use crate::line::{line, LineStyle};
 use crate::color::Color;
 
-pub fn _triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+pub fn wireframe_triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, style: &LineStyle, emit: &mut impl FnMut(Fragment, f32)) {
+    line(v1, v2, style, emit);
+    line(v2, v3, style, emit);
+    line(v3, v1, style, emit);
+}
 
-    fragments.extend(line(v1, v2));
-    fragments.extend(line(v2, v3));
-    fragments.extend(line(v3, v1));
+// Cada vértice como un cuadrado de `size` píxeles a su profundidad
+pub fn triangle_points(v1: &Vertex, v2: &Vertex, v3: &Vertex, size: i32, emit: &mut impl FnMut(Fragment)) {
+    for vertex in [v1, v2, v3] {
+        let center = vertex.transformed_position;
+        let (x0, y0) = (center.x.floor() as i32 - size / 2, center.y.floor() as i32 - size / 2);
 
-    fragments
+        for y in y0..y0 + size {
+            for x in x0..x0 + size {
+                emit(Fragment::new(
+                    Vec2::new(x as f32, y as f32),
+                    Color::new(255, 220, 0),
+                    center.z,
+                    vertex.transformed_normal,
+                    vertex.position,
+                ));
+            }
+        }
+    }
 }
   
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
-    let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
-  
-    let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
-  
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
-  
-    let triangle_area = edge_function(&a, &b, &c);
-  
+// Precisión sub-píxel: las posiciones en pantalla se redondean a 1/256 de píxel
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_ONE: i64 = 1 << SUBPIXEL_BITS;
+const SUBPIXEL_HALF: i64 = SUBPIXEL_ONE / 2;
+
+// Patrones de muestreo estándar para MSAA, en 1/16 de píxel respecto al centro
+pub const MAX_SAMPLES: usize = 8;
+const SAMPLES_1X: [(i64, i64); 1] = [(0, 0)];
+const SAMPLES_2X: [(i64, i64); 2] = [(4, 4), (-4, -4)];
+const SAMPLES_4X: [(i64, i64); 4] = [(-2, -6), (6, -2), (-6, 2), (2, 6)];
+const SAMPLES_8X: [(i64, i64); 8] = [(1, -3), (-1, 3), (5, 1), (-3, -5), (-5, 5), (-7, -1), (3, 7), (7, -7)];
+
+pub fn sample_pattern(samples: usize) -> &'static [(i64, i64)] {
+    match samples {
+        2 => &SAMPLES_2X,
+        4 => &SAMPLES_4X,
+        8 => &SAMPLES_8X,
+        _ => &SAMPLES_1X,
+    }
+}
+
+// Datos de un triángulo en coma fija, orientado con área positiva
+struct TriangleSetup<'a> {
+    vertices: [&'a Vertex; 3],
+    area: i64,
+    // Regla top-left: un centro sobre una arista solo se cuenta si es superior o izquierda
+    bias: [i64; 3],
+    // Derivadas de cada función de arista por sub-píxel en x y en y
+    step_x: [i64; 3],
+    step_y: [i64; 3],
+    bounds: (i64, i64, i64, i64),
+    origin: [(i64, i64); 3],
+    inv_w: [f32; 3],
+}
+
+impl<'a> TriangleSetup<'a> {
+    fn new(v1: &'a Vertex, v2: &'a Vertex, v3: &'a Vertex) -> Option<Self> {
+        let p1 = to_fixed(&v1.transformed_position);
+        let mut p2 = to_fixed(&v2.transformed_position);
+        let mut p3 = to_fixed(&v3.transformed_position);
+        let (mut v2, mut v3) = (v2, v3);
+
+        let mut area = edge_function_fixed(p1, p2, p3);
+        if area == 0 {
+            return None;
+        }
+        // Orientamos todos los triángulos igual para que el área sea positiva
+        if area < 0 {
+            std::mem::swap(&mut p2, &mut p3);
+            std::mem::swap(&mut v2, &mut v3);
+            area = -area;
+        }
+
+        Some(TriangleSetup {
+            vertices: [v1, v2, v3],
+            area,
+            bias: [
+                if is_top_left(p2, p3) { 0 } else { -1 },
+                if is_top_left(p3, p1) { 0 } else { -1 },
+                if is_top_left(p1, p2) { 0 } else { -1 },
+            ],
+            step_x: [p3.1 - p2.1, p1.1 - p3.1, p2.1 - p1.1],
+            step_y: [p2.0 - p3.0, p3.0 - p1.0, p1.0 - p2.0],
+            bounds: (
+                p1.0.min(p2.0).min(p3.0),
+                p1.1.min(p2.1).min(p3.1),
+                p1.0.max(p2.0).max(p3.0),
+                p1.1.max(p2.1).max(p3.1),
+            ),
+            origin: [p2, p3, p1],
+            inv_w: [1.0 / v1.clip_position.w, 1.0 / v2.clip_position.w, 1.0 / v3.clip_position.w],
+        })
+    }
+
+    // Píxeles cuyo centro (más `margin` sub-píxeles) puede caer dentro del triángulo
+    fn pixel_bounds(&self, clip_rect: (i32, i32, i32, i32), margin: i64) -> Option<(i64, i64, i64, i64)> {
+        let min_x = ceil_to_pixel(self.bounds.0 - margin).max(clip_rect.0 as i64);
+        let min_y = ceil_to_pixel(self.bounds.1 - margin).max(clip_rect.1 as i64);
+        let max_x = floor_to_pixel(self.bounds.2 + margin).min(clip_rect.2 as i64);
+        let max_y = floor_to_pixel(self.bounds.3 + margin).min(clip_rect.3 as i64);
+
+        if min_x > max_x || min_y > max_y {
+            None
+        } else {
+            Some((min_x, min_y, max_x, max_y))
+        }
+    }
+
+    // Funciones de arista (con el sesgo top-left) en el centro del píxel
+    fn edges_at(&self, x: i64, y: i64) -> [i64; 3] {
+        let point = (x * SUBPIXEL_ONE + SUBPIXEL_HALF, y * SUBPIXEL_ONE + SUBPIXEL_HALF);
+        let [o1, o2, o3] = self.origin;
+        [
+            edge_function_fixed(o1, o2, point) + self.bias[0],
+            edge_function_fixed(o2, o3, point) + self.bias[1],
+            edge_function_fixed(o3, o1, point) + self.bias[2],
+        ]
+    }
+
+    fn edges_offset(&self, edges: [i64; 3], dx: i64, dy: i64) -> [i64; 3] {
+        [
+            edges[0] + self.step_x[0] * dx + self.step_y[0] * dy,
+            edges[1] + self.step_x[1] * dx + self.step_y[1] * dy,
+            edges[2] + self.step_x[2] * dx + self.step_y[2] * dy,
+        ]
+    }
+
+    // coordenadas baricéntricas en pantalla (válidas para la profundidad)
+    fn barycentric(&self, edges: [i64; 3]) -> (f32, f32, f32) {
+        let area = self.area as f32;
+        (
+            (edges[0] - self.bias[0]) as f32 / area,
+            (edges[1] - self.bias[1]) as f32 / area,
+            (edges[2] - self.bias[2]) as f32 / area,
+        )
+    }
+
+    fn depth(&self, edges: [i64; 3]) -> f32 {
+        let (w1, w2, w3) = self.barycentric(edges);
+        let [v1, v2, v3] = self.vertices;
+        v1.transformed_position.z * w1 + v2.transformed_position.z * w2 + v3.transformed_position.z * w3
+    }
+
+    fn fragment(&self, x: i64, y: i64, edges: [i64; 3]) -> Fragment {
+        let [v1, v2, v3] = self.vertices;
+        let (w1, w2, w3) = self.barycentric(edges);
+
+        // corrección de perspectiva para el resto de atributos
+        let (p1, p2, p3) = (w1 * self.inv_w[0], w2 * self.inv_w[1], w3 * self.inv_w[2]);
+        let inv_sum = 1.0 / (p1 + p2 + p3);
+        let (p1, p2, p3) = (p1 * inv_sum, p2 * inv_sum, p3 * inv_sum);
+
+        let normal = v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3;
+        let normal = normal.normalize();
+
+        Fragment {
+            position: Vec2::new(x as f32, y as f32),
+            color: v1.color * p1 + v2.color * p2 + v3.color * p3,
+            depth: self.depth(edges),
+            normal,
+            vertex_position: v1.position * p1 + v2.position * p2 + v3.position * p3,
+            world_position: v1.world_position * p1 + v2.world_position * p2 + v3.world_position * p3,
+            tex_coords: v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3,
+        }
+    }
+}
+
+// `clip_rect` (min_x, min_y, max_x, max_y, inclusivo) limita los píxeles que se recorren,
+// por ejemplo al tile que se está rasterizando. Cada fragmento se entrega a `emit`
+// en cuanto se genera, sin acumularlos en memoria
+pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, clip_rect: (i32, i32, i32, i32), emit: &mut impl FnMut(Fragment)) {
+    let Some(setup) = TriangleSetup::new(v1, v2, v3) else {
+        return;
+    };
+    let Some((min_x, min_y, max_x, max_y)) = setup.pixel_bounds(clip_rect, 0) else {
+        return;
+    };
+
+    let mut row = setup.edges_at(min_x, min_y);
+
     //  Iterar sobre cada píxel en el cuadro delimitador
     for y in min_y..=max_y {
-      for x in min_x..=max_x {
-        let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
-  
-        // coordenadas
-        let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
-        //verificación del punto
-        if w1 >= 0.0 && w1 <= 1.0 && 
-           w2 >= 0.0 && w2 <= 1.0 &&
-           w3 >= 0.0 && w3 <= 1.0 { 
-            let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
-            let normal = normal.normalize();
-            let intensity = dot(&normal, &light_dir).max(0.0);
-
-            let base_color = Color::new(153, 101, 21); // gris
-            let depth = a.z * w1 + b.z * w2 + c.z * w3;
-
-            let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
-
-            fragments.push(Fragment::new(
-              Vec2::new(x as f32, y as f32),
-              base_color,
-              depth,
-              normal,
-              intensity,
-              vertex_position
-          ));
+        let mut edges = row;
+
+        for x in min_x..=max_x {
+            //verificación del punto
+            if edges.iter().all(|&e| e >= 0) {
+                emit(setup.fragment(x, y, edges));
+            }
+            edges = setup.edges_offset(edges, SUBPIXEL_ONE, 0);
+        }
+
+        row = setup.edges_offset(row, 0, SUBPIXEL_ONE);
+    }
+}
+
+// Variante con varias muestras por píxel: el fragmento se interpola en el centro del
+// píxel y `emit` recibe además la máscara de muestras cubiertas y la profundidad de
+// cada muestra
+pub fn triangle_multisample(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    clip_rect: (i32, i32, i32, i32),
+    samples: usize,
+    emit: &mut impl FnMut(Fragment, u32, &[f32]),
+) {
+    let pattern = sample_pattern(samples);
+    let Some(setup) = TriangleSetup::new(v1, v2, v3) else {
+        return;
+    };
+    let Some((min_x, min_y, max_x, max_y)) = setup.pixel_bounds(clip_rect, SUBPIXEL_HALF) else {
+        return;
+    };
+
+    let sample_step = SUBPIXEL_ONE / 16;
+    let mut depths = [0.0; MAX_SAMPLES];
+    let mut row = setup.edges_at(min_x, min_y);
+
+    for y in min_y..=max_y {
+        let mut edges = row;
+
+        for x in min_x..=max_x {
+            let mut coverage = 0u32;
+            for (sample, &(dx, dy)) in pattern.iter().enumerate() {
+                let sample_edges = setup.edges_offset(edges, dx * sample_step, dy * sample_step);
+                if sample_edges.iter().all(|&e| e >= 0) {
+                    coverage |= 1 << sample;
+                    depths[sample] = setup.depth(sample_edges);
+                }
+            }
+
+            if coverage != 0 {
+                emit(setup.fragment(x, y, edges), coverage, &depths[..pattern.len()]);
+            }
+            edges = setup.edges_offset(edges, SUBPIXEL_ONE, 0);
         }
-      }
+
+        row = setup.edges_offset(row, 0, SUBPIXEL_ONE);
     }
+}
 
-  fragments
+fn to_fixed(v: &Vec3) -> (i64, i64) {
+    (
+        (v.x * SUBPIXEL_ONE as f32).round() as i64,
+        (v.y * SUBPIXEL_ONE as f32).round() as i64,
+    )
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
+// Primer píxel cuyo centro queda en o después de la coordenada (en sub-píxeles)
+fn ceil_to_pixel(value: i64) -> i64 {
+    (value - SUBPIXEL_HALF + SUBPIXEL_ONE - 1).div_euclid(SUBPIXEL_ONE)
+}
+
+// Último píxel cuyo centro queda en o antes de la coordenada (en sub-píxeles)
+fn floor_to_pixel(value: i64) -> i64 {
+    (value - SUBPIXEL_HALF).div_euclid(SUBPIXEL_ONE)
+}
+
+fn edge_function_fixed(a: (i64, i64), b: (i64, i64), c: (i64, i64)) -> i64 {
+    (c.0 - a.0) * (b.1 - a.1) - (c.1 - a.1) * (b.0 - a.0)
+}
+
+// Con el área positiva (y hacia abajo en pantalla), las aristas izquierdas bajan
+// y las superiores son horizontales y van hacia la izquierda
+fn is_top_left(a: (i64, i64), b: (i64, i64)) -> bool {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    dy > 0 || (dy == 0 && dx < 0)
+}
+
+pub fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
     let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
     let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
@@ -68,14 +289,117 @@ fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i3
     (min_x, min_y, max_x, max_y)
 }
 
-fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
-    let w1 = edge_function(b, c, p) / area;
-    let w2 = edge_function(c, a, p) / area;
-    let w3 = edge_function(a, b, p) / area;
-
-    (w1, w2, w3)
+pub fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
+    (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
 }
 
-fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
-    (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::Obj;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const WIDTH: usize = 96;
+    const HEIGHT: usize = 80;
+    const FULL_RECT: (i32, i32, i32, i32) = (0, 0, WIDTH as i32 - 1, HEIGHT as i32 - 1);
+
+    fn screen_vertex(x: f32, y: f32) -> Vertex {
+        Vertex::new(Vec3::new(x, y, 0.5), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0))
+    }
+
+    fn coverage(triangles: &[[Vertex; 3]]) -> Vec<u32> {
+        let mut counts = vec![0; WIDTH * HEIGHT];
+        for tri in triangles {
+            triangle(&tri[0], &tri[1], &tri[2], FULL_RECT, &mut |fragment| {
+                counts[fragment.position.y as usize * WIDTH + fragment.position.x as usize] += 1;
+            });
+        }
+        counts
+    }
+
+    #[test]
+    fn shared_edges_cover_every_pixel_once() {
+        // Malla de rejilla que cubre toda la pantalla con vértices internos desplazados
+        // a medios píxeles, para que muchas aristas pasen justo por centros de píxel
+        let mut rng = StdRng::seed_from_u64(7);
+        let (cols, rows) = (12, 10);
+        let mut grid = Vec::new();
+        for j in 0..=rows {
+            for i in 0..=cols {
+                let mut x = i as f32 * WIDTH as f32 / cols as f32;
+                let mut y = j as f32 * HEIGHT as f32 / rows as f32;
+                if i > 0 && i < cols && j > 0 && j < rows {
+                    x = x.round() + rng.gen_range(-3..=3) as f32 * 0.5;
+                    y = y.round() + rng.gen_range(-3..=3) as f32 * 0.5;
+                }
+                grid.push(screen_vertex(x, y));
+            }
+        }
+
+        let mut triangles = Vec::new();
+        for j in 0..rows {
+            for i in 0..cols {
+                let v00 = &grid[j * (cols + 1) + i];
+                let v10 = &grid[j * (cols + 1) + i + 1];
+                let v01 = &grid[(j + 1) * (cols + 1) + i];
+                let v11 = &grid[(j + 1) * (cols + 1) + i + 1];
+                // Alternamos la diagonal y el orden de los vértices
+                if (i + j) % 2 == 0 {
+                    triangles.push([v00.clone(), v10.clone(), v11.clone()]);
+                    triangles.push([v00.clone(), v01.clone(), v11.clone()]);
+                } else {
+                    triangles.push([v10.clone(), v01.clone(), v00.clone()]);
+                    triangles.push([v10.clone(), v11.clone(), v01.clone()]);
+                }
+            }
+        }
+
+        assert!(coverage(&triangles).iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn pixel_centers_on_shared_edges_are_not_lost() {
+        // Aristas horizontales, verticales y diagonales que pasan exactamente por centros de píxel
+        let triangles = vec![
+            [screen_vertex(0.0, 0.5), screen_vertex(48.5, 0.5), screen_vertex(48.5, 40.5)],
+            [screen_vertex(0.0, 0.5), screen_vertex(48.5, 40.5), screen_vertex(0.0, 40.5)],
+            [screen_vertex(48.5, 0.5), screen_vertex(96.0, 0.5), screen_vertex(48.5, 40.5)],
+            [screen_vertex(96.0, 0.5), screen_vertex(96.0, 40.5), screen_vertex(48.5, 40.5)],
+        ];
+
+        let counts = coverage(&triangles);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let expected = if y < 40 { 1 } else { 0 };
+                assert_eq!(counts[y * WIDTH + x], expected, "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn closed_mesh_front_and_back_faces_cover_each_pixel_once() {
+        let sphere = Obj::load("assets/sphere.obj").expect("Failed to load obj").get_indexed_mesh();
+        let vertices: Vec<Vertex> = sphere
+            .vertices
+            .iter()
+            .map(|v| screen_vertex(v.position.x * 35.0 + 47.37, -v.position.y * 35.0 + 39.81))
+            .collect();
+
+        let mut front = vec![0; WIDTH * HEIGHT];
+        let mut back = vec![0; WIDTH * HEIGHT];
+        for indices in sphere.indices.chunks_exact(3) {
+            let [v1, v2, v3] = [0, 1, 2].map(|corner| &vertices[indices[corner] as usize]);
+            let area = edge_function(&v1.transformed_position, &v2.transformed_position, &v3.transformed_position);
+            let counts = if area > 0.0 { &mut front } else { &mut back };
+            triangle(v1, v2, v3, FULL_RECT, &mut |fragment| {
+                counts[fragment.position.y as usize * WIDTH + fragment.position.x as usize] += 1;
+            });
+        }
+
+        // Una malla cerrada cubre cada píxel de su silueta una vez por delante y una por detrás
+        assert!(front.iter().any(|&count| count > 0));
+        assert!(front.iter().all(|&count| count <= 1));
+        assert_eq!(front, back);
+    }
+}
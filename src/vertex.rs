@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -7,6 +7,8 @@ pub struct Vertex {
   pub normal: Vec3,
   pub tex_coords: Vec2,
   pub color: Color,
+  pub world_position: Vec3,
+  pub clip_position: Vec4,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
 }
@@ -18,6 +20,8 @@ impl Vertex {
       normal,
       tex_coords,
       color: Color::black(),
+      world_position: position,
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
       transformed_position: position,
       transformed_normal: normal,
     }
@@ -29,14 +33,25 @@ impl Vertex {
       normal: Vec3::new(0.0, 0.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
       color,
+      world_position: position,
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
     }
   }
 
-  pub fn set_transformed(&mut self, position: Vec3, normal: Vec3) {
-    self.transformed_position = position;
-    self.transformed_normal = normal;
+  // Interpola todos los atributos entre dos vértices
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+    Vertex {
+      position: self.position.lerp(&other.position, t),
+      normal: self.normal.lerp(&other.normal, t),
+      tex_coords: self.tex_coords.lerp(&other.tex_coords, t),
+      color: self.color.lerp(&other.color, t),
+      world_position: self.world_position.lerp(&other.world_position, t),
+      clip_position: self.clip_position.lerp(&other.clip_position, t),
+      transformed_position: self.transformed_position.lerp(&other.transformed_position, t),
+      transformed_normal: self.transformed_normal.lerp(&other.transformed_normal, t),
+    }
   }
 }
 
@@ -47,6 +62,8 @@ impl Default for Vertex {
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
       color: Color::black(),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
     }